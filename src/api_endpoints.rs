@@ -1,16 +1,38 @@
+use crate::batch::Batch;
+use crate::cancel::{CancelToken, Interrupted};
+use crate::client::UpClient;
+use crate::dates::{self, Tz, DEFAULT_TIMEZONE};
+use crate::models::*;
+use crate::request_sender::*;
+use crate::token::Token;
+use crate::transport::{Method, Timeouts};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDate};
+
+pub(crate) const BASE_URL: &str = "https://api.up.com.au/api/v1";
+
+pub struct ListAccounts {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+}
 
-    use crate::request_sender::*;
-    use crate::models::*;
-    use async_trait::async_trait;
-    use chrono::{DateTime, FixedOffset};
+impl ApiEndpoint for ListAccounts {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
 
-    const BASE_URL: &str = "https://api.up.com.au/api/v1";
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
 
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
 
-    pub struct ListAccounts {
-    url: String,
-    api_key: String,
-    params: Vec<(String, String)>,
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
 }
 
 #[async_trait]
@@ -21,26 +43,40 @@ impl ApiRequest for ListAccounts {
         RequestSender::send_paginate::<Account, ListAccounts>(self).await
     }
 
-    fn get_url(&self) -> &String {
-        &self.url
-    }
-
-    fn get_params(&self) -> &Vec<(String, String)> {
-        &self.params
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<Account, ListAccounts>(self).await
     }
+}
 
-    fn get_api_key(&self) -> &String {
-        &self.api_key
+#[async_trait]
+impl PaginatedRequest for ListAccounts {
+    type Item = Account;
+
+    async fn send_cancellable(
+        self,
+        cancel: &CancelToken,
+    ) -> Result<Vec<Account>, Interrupted<Account>> {
+        RequestSender::send_paginate_cancellable::<Account, ListAccounts>(self, cancel).await
     }
 }
 
 impl ListAccounts {
     pub fn new(api_key: &String) -> ListAccounts {
-        return ListAccounts {
+        ListAccounts::with_client(&UpClient::new(api_key))
+    }
+
+    pub fn with_client(client: &UpClient) -> ListAccounts {
+        ListAccounts {
             url: format!("{}/accounts", BASE_URL).to_string(),
-            api_key: api_key.to_string(),
+            client: client.clone(),
             params: Vec::new(),
-        };
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> ListAccounts {
+        self.client = self.client.timeouts(timeouts);
+        self
     }
 
     pub fn page_size(mut self, page_size: i32) -> ListAccounts {
@@ -55,14 +91,14 @@ impl ListAccounts {
 
     pub fn account_type(mut self, account_type: AccountType) -> ListAccounts {
         self.params
-            .push(("filter[accountType]".to_string(), account_type.to_string()));
+            .push(("filter[accountType]".to_string(), api_value(&account_type)));
         return self;
     }
 
     pub fn ownership_type(mut self, ownership_type: OwnershipType) -> ListAccounts {
         self.params.push((
             String::from("filter[ownershipType]"),
-            ownership_type.to_string(),
+            api_value(&ownership_type),
         ));
         return self;
     }
@@ -70,18 +106,11 @@ impl ListAccounts {
 
 pub struct RetrieveAccount {
     url: String,
-    api_key: String,
+    client: UpClient,
     params: Vec<(String, String)>,
 }
 
-#[async_trait]
-impl ApiRequest for RetrieveAccount {
-    type T = Account;
-
-    async fn send(self) -> Result<Self::T, String> {
-        RequestSender::send::<Account, RetrieveAccount>(self).await
-    }
-
+impl ApiEndpoint for RetrieveAccount {
     fn get_url(&self) -> &String {
         &self.url
     }
@@ -90,27 +119,85 @@ impl ApiRequest for RetrieveAccount {
         &self.params
     }
 
-    fn get_api_key(&self) -> &String {
-        &self.api_key
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl ApiRequest for RetrieveAccount {
+    type T = Account;
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<Account, RetrieveAccount>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<Account, RetrieveAccount>(self).await
     }
 }
 
 impl RetrieveAccount {
     pub fn new(api_key: &String, account_id: String) -> RetrieveAccount {
-        return RetrieveAccount {
+        RetrieveAccount::with_client(&UpClient::new(api_key), account_id)
+    }
+
+    pub fn with_client(client: &UpClient, account_id: String) -> RetrieveAccount {
+        RetrieveAccount {
             url: format!("{}/accounts/{}", BASE_URL, account_id).to_string(),
-            api_key: api_key.to_string(),
+            client: client.clone(),
             params: Vec::new(),
-        };
+        }
+    }
+
+    /// Retrieves the accounts with the given ids, a few at a time.
+    pub fn batch(client: &UpClient, ids: Vec<String>) -> Batch<RetrieveAccount> {
+        Batch::new(client, ids, RetrieveAccount::with_client)
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> RetrieveAccount {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+
+    pub(crate) fn from_url(client: &UpClient, url: String) -> RetrieveAccount {
+        RetrieveAccount {
+            url,
+            client: client.clone(),
+            params: Vec::new(),
+        }
     }
 }
 
 pub struct ListCategories {
     url: String,
-    api_key: String,
+    client: UpClient,
     params: Vec<(String, String)>,
 }
 
+impl ApiEndpoint for ListCategories {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+}
+
 #[async_trait]
 impl ApiRequest for ListCategories {
     type T = Vec<Category>;
@@ -119,26 +206,48 @@ impl ApiRequest for ListCategories {
         RequestSender::send_paginate::<Category, ListCategories>(self).await
     }
 
-    fn get_url(&self) -> &String {
-        &self.url
-    }
-
-    fn get_params(&self) -> &Vec<(String, String)> {
-        &self.params
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<Category, ListCategories>(self).await
     }
+}
 
-    fn get_api_key(&self) -> &String {
-        &self.api_key
+#[async_trait]
+impl PaginatedRequest for ListCategories {
+    type Item = Category;
+
+    async fn send_cancellable(
+        self,
+        cancel: &CancelToken,
+    ) -> Result<Vec<Category>, Interrupted<Category>> {
+        RequestSender::send_paginate_cancellable::<Category, ListCategories>(self, cancel).await
     }
 }
 
 impl ListCategories {
     pub fn new(api_key: &String) -> ListCategories {
-        return ListCategories {
+        ListCategories::with_client(&UpClient::new(api_key))
+    }
+
+    pub fn with_client(client: &UpClient) -> ListCategories {
+        ListCategories {
             url: format!("{}/categories", BASE_URL).to_string(),
-            api_key: api_key.to_string(),
+            client: client.clone(),
             params: Vec::new(),
-        };
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> ListCategories {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+
+    pub(crate) fn from_url(client: &UpClient, url: String) -> ListCategories {
+        ListCategories {
+            url,
+            client: client.clone(),
+            params: Vec::new(),
+        }
     }
 
     pub fn parent(mut self, parent: Category) -> ListCategories {
@@ -146,14 +255,39 @@ impl ListCategories {
             .push(("filter[parent]".to_string(), parent.to_param().to_string()));
         return self;
     }
+
+    /// Like `parent`, with only the id of the parent category, e.g. `good-life`.
+    pub fn parent_id(mut self, parent_id: &str) -> ListCategories {
+        self.params
+            .push(("filter[parent]".to_string(), parent_id.to_string()));
+        self
+    }
 }
 
 pub struct RetrieveCategory {
     url: String,
-    api_key: String,
+    client: UpClient,
     params: Vec<(String, String)>,
 }
 
+impl ApiEndpoint for RetrieveCategory {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+}
+
 #[async_trait]
 impl ApiRequest for RetrieveCategory {
     type T = Category;
@@ -162,6 +296,53 @@ impl ApiRequest for RetrieveCategory {
         RequestSender::send::<Category, RetrieveCategory>(self).await
     }
 
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<Category, RetrieveCategory>(self).await
+    }
+}
+
+impl RetrieveCategory {
+    pub fn new(api_key: &String, category_id: String) -> RetrieveCategory {
+        RetrieveCategory::with_client(&UpClient::new(api_key), category_id)
+    }
+
+    pub fn with_client(client: &UpClient, category_id: String) -> RetrieveCategory {
+        RetrieveCategory {
+            url: format!("{}/categories/{}", BASE_URL, category_id).to_string(),
+            client: client.clone(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Retrieves the categories with the given ids, a few at a time.
+    pub fn batch(client: &UpClient, ids: Vec<String>) -> Batch<RetrieveCategory> {
+        Batch::new(client, ids, RetrieveCategory::with_client)
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> RetrieveCategory {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+
+    pub(crate) fn from_url(client: &UpClient, url: String) -> RetrieveCategory {
+        RetrieveCategory {
+            url,
+            client: client.clone(),
+            params: Vec::new(),
+        }
+    }
+}
+
+/// Sets the category of a transaction, or removes it when `category_id` is `None`.
+pub struct CategorizeTransaction {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+    body: String,
+}
+
+impl ApiEndpoint for CategorizeTransaction {
     fn get_url(&self) -> &String {
         &self.url
     }
@@ -170,31 +351,98 @@ impl ApiRequest for RetrieveCategory {
         &self.params
     }
 
-    fn get_api_key(&self) -> &String {
-        &self.api_key
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+
+    fn get_method(&self) -> Method {
+        Method::Patch
+    }
+
+    fn get_body(&self) -> Option<&String> {
+        Some(&self.body)
     }
 }
 
-impl RetrieveCategory {
-    pub fn new(api_key: &String, category_id: String) -> RetrieveCategory {
-        return RetrieveCategory {
-            url: format!("{}/categories/{}", BASE_URL.to_string(), category_id).to_string(),
-            api_key: api_key.to_string(),
+#[async_trait]
+impl ApiRequest for CategorizeTransaction {
+    type T = ();
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_no_content::<CategorizeTransaction>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_no_content_document::<CategorizeTransaction>(self).await
+    }
+}
+
+impl CategorizeTransaction {
+    pub fn new(
+        api_key: &String,
+        transaction_id: String,
+        category_id: Option<String>,
+    ) -> CategorizeTransaction {
+        CategorizeTransaction::with_client(&UpClient::new(api_key), transaction_id, category_id)
+    }
+
+    pub fn with_client(
+        client: &UpClient,
+        transaction_id: String,
+        category_id: Option<String>,
+    ) -> CategorizeTransaction {
+        CategorizeTransaction {
+            url: format!(
+                "{}/transactions/{}/relationships/category",
+                BASE_URL, transaction_id
+            ),
+            client: client.clone(),
             params: Vec::new(),
-        };
+            body: serde_json::to_string(&Payload {
+                data: category_id.map(|id| ResourceIdentifier {
+                    resource_type: "categories".to_string(),
+                    id,
+                }),
+            })
+            .unwrap(),
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> CategorizeTransaction {
+        self.client = self.client.timeouts(timeouts);
+        self
     }
 }
-/* Todo
-struct CategorizeTransaction {}
 
-impl CategorizeTransaction {}
-*/
 pub struct ListTags {
     url: String,
-    api_key: String,
+    client: UpClient,
     params: Vec<(String, String)>,
 }
 
+impl ApiEndpoint for ListTags {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+}
+
 #[async_trait]
 impl ApiRequest for ListTags {
     type T = Vec<Tag>;
@@ -203,26 +451,37 @@ impl ApiRequest for ListTags {
         RequestSender::send_paginate::<Tag, ListTags>(self).await
     }
 
-    fn get_url(&self) -> &String {
-        &self.url
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<Tag, ListTags>(self).await
     }
+}
 
-    fn get_params(&self) -> &Vec<(String, String)> {
-        &self.params
-    }
+#[async_trait]
+impl PaginatedRequest for ListTags {
+    type Item = Tag;
 
-    fn get_api_key(&self) -> &String {
-        &self.api_key
+    async fn send_cancellable(self, cancel: &CancelToken) -> Result<Vec<Tag>, Interrupted<Tag>> {
+        RequestSender::send_paginate_cancellable::<Tag, ListTags>(self, cancel).await
     }
 }
 
 impl ListTags {
     pub fn new(api_key: &String) -> ListTags {
-        return ListTags {
+        ListTags::with_client(&UpClient::new(api_key))
+    }
+
+    pub fn with_client(client: &UpClient) -> ListTags {
+        ListTags {
             url: format!("{}/tags", BASE_URL).to_string(),
-            api_key: api_key.to_string(),
+            client: client.clone(),
             params: Vec::new(),
-        };
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> ListTags {
+        self.client = self.client.timeouts(timeouts);
+        self
     }
 
     pub fn page_size(mut self, page_size: i32) -> ListTags {
@@ -235,29 +494,16 @@ impl ListTags {
         return self;
     }
 }
-/* Todo
-struct AddTagToTransaction {}
-
-impl AddTagToTransaction {}
-
-struct RemoveTagFromTransaction {}
 
-impl RemoveTagFromTransaction {}
-*/
-pub struct ListTransactions {
+/// Adds tags to a transaction. Tags that do not exist yet are created.
+pub struct AddTagToTransaction {
     url: String,
-    api_key: String,
+    client: UpClient,
     params: Vec<(String, String)>,
+    body: String,
 }
 
-#[async_trait]
-impl ApiRequest for ListTransactions {
-    type T = Vec<Transaction>;
-
-    async fn send(self) -> Result<Self::T, String> {
-        RequestSender::send_paginate::<Transaction, ListTransactions>(self).await
-    }
-
+impl ApiEndpoint for AddTagToTransaction {
     fn get_url(&self) -> &String {
         &self.url
     }
@@ -266,77 +512,73 @@ impl ApiRequest for ListTransactions {
         &self.params
     }
 
-    fn get_api_key(&self) -> &String {
-        &self.api_key
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
     }
-}
 
-impl ListTransactions {
-    pub fn new(api_key: &String) -> ListTransactions {
-        return ListTransactions {
-            url: format!("{}/transactions", BASE_URL).to_string(),
-            api_key: api_key.to_string(),
-            params: Vec::new(),
-        };
+    fn get_client(&self) -> &UpClient {
+        &self.client
     }
 
-    pub fn page_size(mut self, page_size: i32) -> ListTransactions {
-        if page_size > 0 && page_size <= 30 {
-            self.params
-                .push(("page[size]".to_string(), page_size.to_string()));
-        } else {
-            eprintln!("Page size has to be between 1 and 30.");
-        }
-        return self;
+    fn get_method(&self) -> Method {
+        Method::Post
     }
 
-    pub fn status(mut self, transaction_status: TransactionStatus) -> ListTransactions {
-        self.params
-            .push(("filter[status]".to_string(), transaction_status.to_string()));
-        self
+    fn get_body(&self) -> Option<&String> {
+        Some(&self.body)
     }
+}
 
-    pub fn since(mut self, date_time: DateTime<FixedOffset>) -> ListTransactions {
-        self.params
-            .push(("filter[since]".to_string(), date_time.to_rfc3339()));
-        self
+#[async_trait]
+impl ApiRequest for AddTagToTransaction {
+    type T = ();
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_no_content::<AddTagToTransaction>(self).await
     }
 
-    pub fn until(mut self, date_time: DateTime<FixedOffset>) -> ListTransactions {
-        self.params
-            .push(("filter[until]".to_string(), date_time.to_rfc3339()));
-        self
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_no_content_document::<AddTagToTransaction>(self).await
     }
+}
 
-    pub fn category(mut self, category: Category) -> ListTransactions {
-        self.params.push((
-            "filter[category]".to_string(),
-            category.to_param().to_string(),
-        ));
-        self
+impl AddTagToTransaction {
+    pub fn new(api_key: &String, transaction_id: String, tags: Vec<String>) -> AddTagToTransaction {
+        AddTagToTransaction::with_client(&UpClient::new(api_key), transaction_id, tags)
     }
 
-    pub fn tag(mut self, tag: Tag) -> ListTransactions {
-        self.params
-            .push(("filter[tag".to_string(), tag.to_param().to_string()));
+    pub fn with_client(
+        client: &UpClient,
+        transaction_id: String,
+        tags: Vec<String>,
+    ) -> AddTagToTransaction {
+        AddTagToTransaction {
+            url: format!(
+                "{}/transactions/{}/relationships/tags",
+                BASE_URL, transaction_id
+            ),
+            client: client.clone(),
+            params: Vec::new(),
+            body: tag_payload(tags),
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> AddTagToTransaction {
+        self.client = self.client.timeouts(timeouts);
         self
     }
 }
 
-pub struct RetrieveTransaction {
+/// Removes tags from a transaction.
+pub struct RemoveTagFromTransaction {
     url: String,
-    api_key: String,
+    client: UpClient,
     params: Vec<(String, String)>,
+    body: String,
 }
 
-#[async_trait]
-impl ApiRequest for RetrieveTransaction {
-    type T = Transaction;
-
-    async fn send(self) -> Result<Self::T, String> {
-        RequestSender::send::<Transaction, RetrieveTransaction>(self).await
-    }
-
+impl ApiEndpoint for RemoveTagFromTransaction {
     fn get_url(&self) -> &String {
         &self.url
     }
@@ -345,17 +587,865 @@ impl ApiRequest for RetrieveTransaction {
         &self.params
     }
 
-    fn get_api_key(&self) -> &String {
-        &self.api_key
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+
+    fn get_method(&self) -> Method {
+        Method::Delete
+    }
+
+    fn get_body(&self) -> Option<&String> {
+        Some(&self.body)
     }
 }
 
-impl RetrieveTransaction {
-    pub fn new(api_key: &String, transaction_id: String) -> RetrieveTransaction {
-        return RetrieveTransaction {
-            url: format!("{}/transactions/{}", BASE_URL, transaction_id).to_string(),
-            api_key: api_key.to_string(),
+#[async_trait]
+impl ApiRequest for RemoveTagFromTransaction {
+    type T = ();
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_no_content::<RemoveTagFromTransaction>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_no_content_document::<RemoveTagFromTransaction>(self).await
+    }
+}
+
+impl RemoveTagFromTransaction {
+    pub fn new(
+        api_key: &String,
+        transaction_id: String,
+        tags: Vec<String>,
+    ) -> RemoveTagFromTransaction {
+        RemoveTagFromTransaction::with_client(&UpClient::new(api_key), transaction_id, tags)
+    }
+
+    pub fn with_client(
+        client: &UpClient,
+        transaction_id: String,
+        tags: Vec<String>,
+    ) -> RemoveTagFromTransaction {
+        RemoveTagFromTransaction {
+            url: format!(
+                "{}/transactions/{}/relationships/tags",
+                BASE_URL, transaction_id
+            ),
+            client: client.clone(),
+            params: Vec::new(),
+            body: tag_payload(tags),
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> RemoveTagFromTransaction {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+}
+
+fn tag_payload(tags: Vec<String>) -> String {
+    let data: Vec<ResourceIdentifier> = tags
+        .into_iter()
+        .map(|id| ResourceIdentifier {
+            resource_type: "tags".to_string(),
+            id,
+        })
+        .collect();
+    serde_json::to_string(&Payload { data }).unwrap()
+}
+
+pub struct ListTransactions {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+    timezone: Tz,
+    dates: Option<DateRange>,
+    date_params: Vec<(String, String)>,
+}
+
+/// The dates asked for with `on_date`, `in_month`, `last_n_days` or `between_dates`, kept so they
+/// can be resolved again when the timezone changes.
+#[derive(Clone, Copy)]
+enum DateRange {
+    Between(NaiveDate, NaiveDate),
+    Month(i32, u32),
+    LastDays(u32),
+}
+
+impl ApiEndpoint for ListTransactions {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl ApiRequest for ListTransactions {
+    type T = Vec<Transaction>;
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_paginate::<Transaction, ListTransactions>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<Transaction, ListTransactions>(self).await
+    }
+}
+
+#[async_trait]
+impl PaginatedRequest for ListTransactions {
+    type Item = Transaction;
+
+    async fn send_cancellable(
+        self,
+        cancel: &CancelToken,
+    ) -> Result<Vec<Transaction>, Interrupted<Transaction>> {
+        RequestSender::send_paginate_cancellable::<Transaction, ListTransactions>(self, cancel)
+            .await
+    }
+}
+
+impl ListTransactions {
+    pub fn new(api_key: &String) -> ListTransactions {
+        ListTransactions::with_client(&UpClient::new(api_key))
+    }
+
+    pub fn with_client(client: &UpClient) -> ListTransactions {
+        ListTransactions {
+            url: format!("{}/transactions", BASE_URL).to_string(),
+            client: client.clone(),
             params: Vec::new(),
+            timezone: DEFAULT_TIMEZONE,
+            dates: None,
+            date_params: Vec::new(),
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> ListTransactions {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+
+    pub(crate) fn from_url(client: &UpClient, url: String) -> ListTransactions {
+        ListTransactions {
+            url,
+            client: client.clone(),
+            params: Vec::new(),
+            timezone: DEFAULT_TIMEZONE,
+            dates: None,
+            date_params: Vec::new(),
+        }
+    }
+
+    /// Only transactions of the account with the given id.
+    pub fn account(mut self, account_id: &str) -> ListTransactions {
+        self.url = format!("{}/accounts/{}/transactions", BASE_URL, account_id);
+        self
+    }
+
+    pub fn page_size(mut self, page_size: i32) -> ListTransactions {
+        if page_size > 0 && page_size <= 30 {
+            self.params
+                .push(("page[size]".to_string(), page_size.to_string()));
+        } else {
+            eprintln!("Page size has to be between 1 and 30.");
+        }
+        return self;
+    }
+
+    pub fn status(mut self, transaction_status: TransactionStatus) -> ListTransactions {
+        self.params
+            .push(("filter[status]".to_string(), api_value(&transaction_status)));
+        self
+    }
+
+    pub fn since(mut self, date_time: DateTime<FixedOffset>) -> ListTransactions {
+        self.params
+            .push(("filter[since]".to_string(), date_time.to_rfc3339()));
+        self
+    }
+
+    pub fn until(mut self, date_time: DateTime<FixedOffset>) -> ListTransactions {
+        self.params
+            .push(("filter[until]".to_string(), date_time.to_rfc3339()));
+        self
+    }
+
+    /// Sets the timezone used by `on_date`, `in_month`, `last_n_days` and `between_dates`,
+    /// whether it is called before or after them. Defaults to Australia/Melbourne.
+    pub fn timezone(mut self, timezone: Tz) -> ListTransactions {
+        self.timezone = timezone;
+        match self.dates {
+            None => self,
+            Some(dates) => self.dates(dates),
+        }
+    }
+
+    /// Only transactions created on `date`.
+    pub fn on_date(self, date: NaiveDate) -> ListTransactions {
+        self.between_dates(date, date)
+    }
+
+    /// Only transactions created in the given month.
+    pub fn in_month(self, year: i32, month: u32) -> ListTransactions {
+        self.dates(DateRange::Month(year, month))
+    }
+
+    /// Only transactions created today or in the `days - 1` days before it. `days` has to be at
+    /// least 1.
+    pub fn last_n_days(self, days: u32) -> ListTransactions {
+        if days == 0 {
+            eprintln!("The number of days has to be at least 1.");
+            return self;
+        }
+        self.dates(DateRange::LastDays(days))
+    }
+
+    /// Only transactions created between `first` and `last`, both inclusive.
+    pub fn between_dates(self, first: NaiveDate, last: NaiveDate) -> ListTransactions {
+        if last < first {
+            eprintln!("The last date has to be on or after the first date.");
+            return self;
+        }
+        self.dates(DateRange::Between(first, last))
+    }
+
+    /// Replaces the `filter[since]` and `filter[until]` of the previous dates with those of
+    /// `dates` in the current timezone.
+    fn dates(mut self, dates: DateRange) -> ListTransactions {
+        let timezone = self.timezone;
+        let bounds = match dates {
+            DateRange::Between(first, last) => match dates::day_bounds(first, last, timezone) {
+                None => Err("The last date is too far in the future."),
+                Some((since, until)) => Ok((since, Some(until))),
+            },
+            DateRange::Month(year, month) => match dates::month_bounds(year, month, timezone) {
+                None => Err("Month has to be between 1 and 12."),
+                Some((since, until)) => Ok((since, Some(until))),
+            },
+            DateRange::LastDays(days) => {
+                let first = dates::first_of_last_n_days(dates::today(timezone), days);
+                Ok((dates::start_of_day(first, timezone), None))
+            }
+        };
+        let (since, until) = match bounds {
+            Err(message) => {
+                eprintln!("{}", message);
+                return self;
+            }
+            Ok(bounds) => bounds,
         };
+        let previous = std::mem::take(&mut self.date_params);
+        self.params.retain(|param| !previous.contains(param));
+        self.date_params
+            .push(("filter[since]".to_string(), since.to_rfc3339()));
+        if let Some(until) = until {
+            self.date_params
+                .push(("filter[until]".to_string(), until.to_rfc3339()));
+        }
+        self.params.extend(self.date_params.iter().cloned());
+        self.dates = Some(dates);
+        self
+    }
+
+    pub fn category(mut self, category: Category) -> ListTransactions {
+        self.params.push((
+            "filter[category]".to_string(),
+            category.to_param().to_string(),
+        ));
+        self
+    }
+
+    pub fn tag(mut self, tag: Tag) -> ListTransactions {
+        self.params
+            .push(("filter[tag]".to_string(), tag.to_param().to_string()));
+        self
+    }
+
+    /// Like `category`, with only the id of the category, e.g. `takeaway`.
+    pub fn category_id(self, category_id: &str) -> ListTransactions {
+        self.param("filter[category]", category_id)
+    }
+
+    /// Like `tag`, with only the id of the tag, which is its label.
+    pub fn tag_id(self, tag: &str) -> ListTransactions {
+        self.param("filter[tag]", tag)
+    }
+
+    pub(crate) fn param(mut self, name: &str, value: &str) -> ListTransactions {
+        self.params.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+pub struct RetrieveTransaction {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+}
+
+impl ApiEndpoint for RetrieveTransaction {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl ApiRequest for RetrieveTransaction {
+    type T = Transaction;
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<Transaction, RetrieveTransaction>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<Transaction, RetrieveTransaction>(self).await
+    }
+}
+
+impl RetrieveTransaction {
+    pub fn new(api_key: &String, transaction_id: String) -> RetrieveTransaction {
+        RetrieveTransaction::with_client(&UpClient::new(api_key), transaction_id)
+    }
+
+    pub fn with_client(client: &UpClient, transaction_id: String) -> RetrieveTransaction {
+        RetrieveTransaction {
+            url: format!("{}/transactions/{}", BASE_URL, transaction_id).to_string(),
+            client: client.clone(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Retrieves the transactions with the given ids, a few at a time.
+    pub fn batch(client: &UpClient, ids: Vec<String>) -> Batch<RetrieveTransaction> {
+        Batch::new(client, ids, RetrieveTransaction::with_client)
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> RetrieveTransaction {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+}
+
+/// Checks that the API can be reached and that the token is valid.
+pub struct Ping {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+}
+
+impl ApiEndpoint for Ping {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl ApiRequest for Ping {
+    type T = PingMeta;
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_meta::<PingMeta, Ping>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_meta_document::<PingMeta, Ping>(self).await
+    }
+}
+
+impl Ping {
+    pub fn new(api_key: &String) -> Ping {
+        Ping::with_client(&UpClient::new(api_key))
+    }
+
+    pub fn with_client(client: &UpClient) -> Ping {
+        Ping {
+            url: format!("{}/util/ping", BASE_URL),
+            client: client.clone(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Ping {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+}
+
+pub struct ListWebhooks {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+}
+
+impl ApiEndpoint for ListWebhooks {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl ApiRequest for ListWebhooks {
+    type T = Vec<Webhook>;
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_paginate::<Webhook, ListWebhooks>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<Webhook, ListWebhooks>(self).await
+    }
+}
+
+#[async_trait]
+impl PaginatedRequest for ListWebhooks {
+    type Item = Webhook;
+
+    async fn send_cancellable(
+        self,
+        cancel: &CancelToken,
+    ) -> Result<Vec<Webhook>, Interrupted<Webhook>> {
+        RequestSender::send_paginate_cancellable::<Webhook, ListWebhooks>(self, cancel).await
+    }
+}
+
+impl ListWebhooks {
+    pub fn new(api_key: &String) -> ListWebhooks {
+        ListWebhooks::with_client(&UpClient::new(api_key))
+    }
+
+    pub fn with_client(client: &UpClient) -> ListWebhooks {
+        ListWebhooks {
+            url: format!("{}/webhooks", BASE_URL),
+            client: client.clone(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> ListWebhooks {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+
+    pub fn page_size(mut self, page_size: i32) -> ListWebhooks {
+        if page_size > 0 {
+            self.params
+                .push(("page[size]".to_string(), page_size.to_string()));
+        } else {
+            eprintln!("Page size has to be greater than 0.");
+        }
+        self
+    }
+}
+
+/// Creates a webhook that is sent the events of the account. The `secret_key` of the returned
+/// webhook is only available in this response.
+pub struct CreateWebhook {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+    body: String,
+    webhook_url: String,
+    description: Option<String>,
+}
+
+impl ApiEndpoint for CreateWebhook {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+
+    fn get_method(&self) -> Method {
+        Method::Post
+    }
+
+    fn get_body(&self) -> Option<&String> {
+        Some(&self.body)
+    }
+}
+
+#[async_trait]
+impl ApiRequest for CreateWebhook {
+    type T = Webhook;
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<Webhook, CreateWebhook>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<Webhook, CreateWebhook>(self).await
+    }
+}
+
+impl CreateWebhook {
+    pub fn new(api_key: &String, webhook_url: String) -> CreateWebhook {
+        CreateWebhook::with_client(&UpClient::new(api_key), webhook_url)
+    }
+
+    pub fn with_client(client: &UpClient, webhook_url: String) -> CreateWebhook {
+        CreateWebhook {
+            url: format!("{}/webhooks", BASE_URL),
+            client: client.clone(),
+            params: Vec::new(),
+            body: String::new(),
+            webhook_url,
+            description: None,
+        }
+        .with_body()
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> CreateWebhook {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> CreateWebhook {
+        self.description = Some(description.to_string());
+        self.with_body()
+    }
+
+    fn with_body(mut self) -> CreateWebhook {
+        self.body = serde_json::to_string(&Payload {
+            data: WebhookInput {
+                attributes: WebhookInputAttributes {
+                    url: self.webhook_url.to_string(),
+                    description: self.description.clone(),
+                },
+            },
+        })
+        .unwrap();
+        self
+    }
+}
+
+pub struct RetrieveWebhook {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+}
+
+impl ApiEndpoint for RetrieveWebhook {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl ApiRequest for RetrieveWebhook {
+    type T = Webhook;
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<Webhook, RetrieveWebhook>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<Webhook, RetrieveWebhook>(self).await
+    }
+}
+
+impl RetrieveWebhook {
+    pub fn new(api_key: &String, webhook_id: String) -> RetrieveWebhook {
+        RetrieveWebhook::with_client(&UpClient::new(api_key), webhook_id)
+    }
+
+    pub fn with_client(client: &UpClient, webhook_id: String) -> RetrieveWebhook {
+        RetrieveWebhook {
+            url: format!("{}/webhooks/{}", BASE_URL, webhook_id),
+            client: client.clone(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> RetrieveWebhook {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+}
+
+pub struct DeleteWebhook {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+}
+
+impl ApiEndpoint for DeleteWebhook {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+
+    fn get_method(&self) -> Method {
+        Method::Delete
+    }
+}
+
+#[async_trait]
+impl ApiRequest for DeleteWebhook {
+    type T = ();
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_no_content::<DeleteWebhook>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_no_content_document::<DeleteWebhook>(self).await
+    }
+}
+
+impl DeleteWebhook {
+    pub fn new(api_key: &String, webhook_id: String) -> DeleteWebhook {
+        DeleteWebhook::with_client(&UpClient::new(api_key), webhook_id)
+    }
+
+    pub fn with_client(client: &UpClient, webhook_id: String) -> DeleteWebhook {
+        DeleteWebhook {
+            url: format!("{}/webhooks/{}", BASE_URL, webhook_id),
+            client: client.clone(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> DeleteWebhook {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+}
+
+/// Sends a `PING` event to a webhook.
+pub struct PingWebhook {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+}
+
+impl ApiEndpoint for PingWebhook {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+
+    fn get_method(&self) -> Method {
+        Method::Post
+    }
+}
+
+#[async_trait]
+impl ApiRequest for PingWebhook {
+    type T = WebhookEvent;
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<WebhookEvent, PingWebhook>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<WebhookEvent, PingWebhook>(self).await
+    }
+}
+
+impl PingWebhook {
+    pub fn new(api_key: &String, webhook_id: String) -> PingWebhook {
+        PingWebhook::with_client(&UpClient::new(api_key), webhook_id)
+    }
+
+    pub fn with_client(client: &UpClient, webhook_id: String) -> PingWebhook {
+        PingWebhook {
+            url: format!("{}/webhooks/{}/ping", BASE_URL, webhook_id),
+            client: client.clone(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> PingWebhook {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+}
+
+/// Lists the deliveries of the events of a webhook, newest first.
+pub struct ListWebhookLogs {
+    url: String,
+    client: UpClient,
+    params: Vec<(String, String)>,
+}
+
+impl ApiEndpoint for ListWebhookLogs {
+    fn get_url(&self) -> &String {
+        &self.url
+    }
+
+    fn get_params(&self) -> &Vec<(String, String)> {
+        &self.params
+    }
+
+    fn get_token(&self) -> &Token {
+        self.client.get_token()
+    }
+
+    fn get_client(&self) -> &UpClient {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl ApiRequest for ListWebhookLogs {
+    type T = Vec<WebhookDeliveryLog>;
+
+    async fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_paginate::<WebhookDeliveryLog, ListWebhookLogs>(self).await
+    }
+
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<WebhookDeliveryLog, ListWebhookLogs>(self).await
+    }
+}
+
+#[async_trait]
+impl PaginatedRequest for ListWebhookLogs {
+    type Item = WebhookDeliveryLog;
+
+    async fn send_cancellable(
+        self,
+        cancel: &CancelToken,
+    ) -> Result<Vec<WebhookDeliveryLog>, Interrupted<WebhookDeliveryLog>> {
+        RequestSender::send_paginate_cancellable::<WebhookDeliveryLog, ListWebhookLogs>(
+            self, cancel,
+        )
+        .await
+    }
+}
+
+impl ListWebhookLogs {
+    pub fn new(api_key: &String, webhook_id: String) -> ListWebhookLogs {
+        ListWebhookLogs::with_client(&UpClient::new(api_key), webhook_id)
+    }
+
+    pub fn with_client(client: &UpClient, webhook_id: String) -> ListWebhookLogs {
+        ListWebhookLogs {
+            url: format!("{}/webhooks/{}/logs", BASE_URL, webhook_id),
+            client: client.clone(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Overrides the timeouts of the client for this request.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> ListWebhookLogs {
+        self.client = self.client.timeouts(timeouts);
+        self
+    }
+
+    pub fn page_size(mut self, page_size: i32) -> ListWebhookLogs {
+        if page_size > 0 {
+            self.params
+                .push(("page[size]".to_string(), page_size.to_string()));
+        } else {
+            eprintln!("Page size has to be greater than 0.");
+        }
+        self
     }
 }
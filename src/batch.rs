@@ -0,0 +1,100 @@
+use crate::client::UpClient;
+use crate::request_sender::ApiRequest;
+use futures_util::stream::{self, StreamExt};
+
+/// How many requests a `Batch` sends at once unless told otherwise.
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Retrieves many resources of one type through a single client, e.g.
+/// `client.retrieve_transactions(ids)`.
+///
+/// Up to `concurrency` requests are in flight at once, and they are still subject to the rate
+/// limit of the client.
+pub struct Batch<R> {
+    client: UpClient,
+    ids: Vec<String>,
+    concurrency: usize,
+    retrieve: fn(&UpClient, String) -> R,
+}
+
+/// The outcome of retrieving one resource of a `Batch`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchResult<T> {
+    pub id: String,
+    pub result: Result<T, String>,
+}
+
+impl<R> Batch<R> {
+    pub(crate) fn new(
+        client: &UpClient,
+        ids: Vec<String>,
+        retrieve: fn(&UpClient, String) -> R,
+    ) -> Batch<R> {
+        Batch {
+            client: client.clone(),
+            ids,
+            concurrency: DEFAULT_CONCURRENCY,
+            retrieve,
+        }
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Batch<R> {
+        if concurrency > 0 {
+            self.concurrency = concurrency;
+        } else {
+            eprintln!("Concurrency has to be at least 1.");
+        }
+        self
+    }
+}
+
+impl<R: ApiRequest> Batch<R> {
+    /// Retrieves every resource. The results are in the order of the ids, and a failed request
+    /// (e.g. a `404` for an unknown id) only fails its own result.
+    pub async fn send(self) -> Vec<BatchResult<R::T>> {
+        let client = self.client;
+        let retrieve = self.retrieve;
+        stream::iter(self.ids)
+            .map(|id| {
+                let request = retrieve(&client, id.clone());
+                async move {
+                    BatchResult {
+                        result: request.send().await,
+                        id,
+                    }
+                }
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api_endpoints::RetrieveTransaction;
+    use crate::models::AccountType;
+    use crate::testing::{self, MockUpApi};
+
+    #[tokio::test]
+    async fn keeps_the_order_and_failures_of_the_ids() {
+        let created = testing::at("2022-10-01T09:00:00+10:00");
+        let mock = MockUpApi::new()
+            .account(testing::account("a", "A", AccountType::Saver, 1))
+            .transaction(testing::transaction("t1", "a", "One", -100, created))
+            .transaction(testing::transaction("t2", "a", "Two", -200, created))
+            .transaction(testing::transaction("t3", "a", "Three", -300, created));
+        let ids = ["t3", "missing", "t1", "t2"].map(String::from).to_vec();
+        let results = RetrieveTransaction::batch(&mock.client(), ids)
+            .concurrency(2)
+            .send()
+            .await;
+
+        let ids: Vec<&str> = results.iter().map(|result| result.id.as_str()).collect();
+        assert_eq!(ids, vec!["t3", "missing", "t1", "t2"]);
+        assert_eq!(results[0].result.as_ref().unwrap().id, "t3");
+        assert!(results[1].result.is_err());
+        assert_eq!(results[3].result.as_ref().unwrap().id, "t2");
+        assert_eq!(mock.get_requests().len(), 4);
+    }
+}
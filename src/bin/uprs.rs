@@ -0,0 +1,654 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::path::PathBuf;
+use uprs::client::UpClient;
+use uprs::dates::{Tz, DEFAULT_TIMEZONE};
+use uprs::models::*;
+use uprs::request_sender::ApiRequest;
+use uprs::token::Token;
+
+/// Command-line access to the Up Bank API.
+#[derive(Parser, Debug)]
+#[command(name = "uprs", version)]
+struct Cli {
+    /// A file holding the personal access token, readable only by its owner, or - to read it from
+    /// standard input. Without it the token is read from UP_API_TOKEN.
+    #[arg(long, global = true)]
+    token_file: Option<PathBuf>,
+    /// The personal access token. It is visible to other processes and kept in the shell
+    /// history, so prefer --token-file or UP_API_TOKEN.
+    #[arg(long, global = true, conflicts_with = "token_file")]
+    token: Option<String>,
+    /// How to print the results.
+    #[arg(long, value_enum, default_value_t = Output::Table, global = true)]
+    output: Output,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Output {
+    Table,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Checks that the token is valid.
+    Ping,
+    /// Lists the accounts.
+    Accounts {
+        #[arg(long, value_enum)]
+        account_type: Option<AccountTypeArg>,
+        #[arg(long, value_enum)]
+        ownership: Option<OwnershipArg>,
+    },
+    /// Lists the categories.
+    Categories {
+        /// Only the children of this category.
+        #[arg(long)]
+        parent: Option<String>,
+    },
+    /// Lists the tags.
+    Tags,
+    /// Lists transactions, newest first.
+    Transactions(TransactionFilters),
+    /// Shows a transaction.
+    Transaction { id: String },
+    /// Sets the category of a transaction, or removes it when no category is given.
+    Categorize {
+        transaction_id: String,
+        category_id: Option<String>,
+    },
+    /// Adds tags to or removes tags from a transaction.
+    Tag {
+        #[command(subcommand)]
+        command: TagCommand,
+    },
+    /// Manages webhooks.
+    Webhooks {
+        #[command(subcommand)]
+        command: WebhookCommand,
+    },
+}
+
+#[derive(Args, Debug)]
+struct TransactionFilters {
+    /// Only transactions of this account.
+    #[arg(long)]
+    account: Option<String>,
+    /// Only held or only settled transactions.
+    #[arg(long, value_enum)]
+    status: Option<StatusArg>,
+    /// Only transactions created at or after this time, e.g. 2022-10-01T00:00:00+10:00.
+    #[arg(long, value_parser = DateTime::parse_from_rfc3339)]
+    since: Option<DateTime<FixedOffset>>,
+    /// Only transactions created before this time.
+    #[arg(long, value_parser = DateTime::parse_from_rfc3339)]
+    until: Option<DateTime<FixedOffset>>,
+    /// The timezone of --date, --month, --last-days, --from and --to.
+    #[arg(long, value_parser = parse_timezone, default_value = "Australia/Melbourne")]
+    timezone: Tz,
+    /// Only transactions created on this day, e.g. 2022-10-01.
+    #[arg(long, conflicts_with_all = ["since", "until", "month", "last_days", "from"])]
+    date: Option<NaiveDate>,
+    /// Only transactions created in this month, e.g. 2022-10.
+    #[arg(
+        long,
+        value_parser = parse_month,
+        conflicts_with_all = ["since", "until", "last_days", "from"]
+    )]
+    month: Option<(i32, u32)>,
+    /// Only transactions created today or in the days before it, up to 36500 days.
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u32).range(1..=36500),
+        conflicts_with_all = ["since", "from"]
+    )]
+    last_days: Option<u32>,
+    /// Only transactions created on or after this day. Needs --to.
+    #[arg(long, requires = "to", conflicts_with_all = ["since", "until"])]
+    from: Option<NaiveDate>,
+    /// Only transactions created on or before this day. Needs --from.
+    #[arg(long, requires = "from")]
+    to: Option<NaiveDate>,
+    /// Only transactions in this category, e.g. takeaway.
+    #[arg(long)]
+    category: Option<String>,
+    /// Only transactions with this tag.
+    #[arg(long)]
+    tag: Option<String>,
+    /// The number of transactions fetched per request.
+    #[arg(long)]
+    page_size: Option<i32>,
+}
+
+#[derive(Subcommand, Debug)]
+enum TagCommand {
+    /// Adds tags to a transaction, creating tags that do not exist yet.
+    Add {
+        transaction_id: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+    /// Removes tags from a transaction.
+    Remove {
+        transaction_id: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum WebhookCommand {
+    /// Lists the webhooks.
+    List,
+    /// Creates a webhook. Its secret key is only shown now.
+    Create {
+        url: String,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Shows a webhook.
+    Get { id: String },
+    /// Deletes a webhook.
+    Delete { id: String },
+    /// Sends a PING event to a webhook.
+    Ping { id: String },
+    /// Lists the deliveries to a webhook, newest first.
+    Logs { id: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum AccountTypeArg {
+    Saver,
+    Transactional,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum OwnershipArg {
+    Individual,
+    Joint,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum StatusArg {
+    Held,
+    Settled,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(error) = run(cli).await {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let token = match (&cli.token_file, &cli.token) {
+        (Some(path), _) if path.as_os_str() == "-" => Token::from_stdin()?,
+        (Some(path), _) => Token::from_file(path)?,
+        (None, Some(token)) if !token.trim().is_empty() => Token::new(token),
+        _ => Token::from_env()
+            .map_err(|e| format!("{} Give the token with --token-file or UP_API_TOKEN.", e))?,
+    };
+    let client = UpClient::with_token(token);
+    let output = cli.output;
+    match cli.command {
+        Command::Ping => {
+            let meta = client.ping().send().await?;
+            print(
+                output,
+                &meta,
+                || vec![vec![meta.status_emoji.to_string(), meta.id.to_string()]],
+                &["STATUS", "ID"],
+            )?;
+        }
+        Command::Accounts {
+            account_type,
+            ownership,
+        } => {
+            let mut request = client.list_accounts();
+            if let Some(account_type) = account_type {
+                request = request.account_type(match account_type {
+                    AccountTypeArg::Saver => AccountType::Saver,
+                    AccountTypeArg::Transactional => AccountType::Transactional,
+                });
+            }
+            if let Some(ownership) = ownership {
+                request = request.ownership_type(match ownership {
+                    OwnershipArg::Individual => OwnershipType::Individual,
+                    OwnershipArg::Joint => OwnershipType::Joint,
+                });
+            }
+            let accounts = request.send().await?;
+            print(
+                output,
+                &accounts,
+                || {
+                    accounts
+                        .iter()
+                        .map(|account| {
+                            let attributes = &account.attributes;
+                            vec![
+                                account.id.to_string(),
+                                attributes.display_name.to_string(),
+                                attributes.account_type.to_string(),
+                                attributes.ownership_type.to_string(),
+                                attributes.balance.value.to_string(),
+                            ]
+                        })
+                        .collect()
+                },
+                &["ID", "NAME", "TYPE", "OWNERSHIP", "BALANCE"],
+            )?;
+        }
+        Command::Categories { parent } => {
+            let mut request = client.list_categories();
+            if let Some(parent) = parent {
+                request = request.parent_id(&parent);
+            }
+            let categories = request.send().await?;
+            print(
+                output,
+                &categories,
+                || {
+                    categories
+                        .iter()
+                        .map(|category| {
+                            vec![
+                                category.id.to_string(),
+                                category.attributes.name.to_string(),
+                                category.parent_id().cloned().unwrap_or_default(),
+                            ]
+                        })
+                        .collect()
+                },
+                &["ID", "NAME", "PARENT"],
+            )?;
+        }
+        Command::Tags => {
+            let tags = client.list_tags().send().await?;
+            print(
+                output,
+                &tags,
+                || tags.iter().map(|tag| vec![tag.id.to_string()]).collect(),
+                &["ID"],
+            )?;
+        }
+        Command::Transactions(filters) => {
+            let timezone = filters.timezone;
+            let transactions = list_transactions(&client, filters).await?;
+            print(
+                output,
+                &transactions,
+                || {
+                    transactions
+                        .iter()
+                        .map(|transaction| transaction_row(transaction, timezone))
+                        .collect()
+                },
+                &[
+                    "ID",
+                    "CREATED",
+                    "STATUS",
+                    "DESCRIPTION",
+                    "AMOUNT",
+                    "CATEGORY",
+                ],
+            )?;
+        }
+        Command::Transaction { id } => {
+            let transaction = client.retrieve_transaction(id).send().await?;
+            print(
+                output,
+                &transaction,
+                || transaction_details(&transaction),
+                &["FIELD", "VALUE"],
+            )?;
+        }
+        Command::Categorize {
+            transaction_id,
+            category_id,
+        } => {
+            client
+                .categorize_transaction(transaction_id, category_id)
+                .send()
+                .await?
+        }
+        Command::Tag { command } => match command {
+            TagCommand::Add {
+                transaction_id,
+                tags,
+            } => {
+                client
+                    .add_tag_to_transaction(transaction_id, tags)
+                    .send()
+                    .await?
+            }
+            TagCommand::Remove {
+                transaction_id,
+                tags,
+            } => {
+                client
+                    .remove_tag_from_transaction(transaction_id, tags)
+                    .send()
+                    .await?
+            }
+        },
+        Command::Webhooks { command } => webhooks(&client, output, command).await?,
+    }
+    Ok(())
+}
+
+async fn list_transactions(
+    client: &UpClient,
+    filters: TransactionFilters,
+) -> Result<Vec<Transaction>, String> {
+    let mut request = client.list_transactions().timezone(filters.timezone);
+    if let Some(account) = &filters.account {
+        request = request.account(account);
+    }
+    if let Some(page_size) = filters.page_size {
+        request = request.page_size(page_size);
+    }
+    if let Some(status) = filters.status {
+        request = request.status(match status {
+            StatusArg::Held => TransactionStatus::Held,
+            StatusArg::Settled => TransactionStatus::Settled,
+        });
+    }
+    if let Some(since) = filters.since {
+        request = request.since(since);
+    }
+    if let Some(until) = filters.until {
+        request = request.until(until);
+    }
+    if let Some(date) = filters.date {
+        request = request.on_date(date);
+    }
+    if let Some((year, month)) = filters.month {
+        request = request.in_month(year, month);
+    }
+    if let Some(days) = filters.last_days {
+        request = request.last_n_days(days);
+    }
+    if let (Some(first), Some(last)) = (filters.from, filters.to) {
+        request = request.between_dates(first, last);
+    }
+    if let Some(category) = filters.category {
+        request = request.category_id(&category);
+    }
+    if let Some(tag) = filters.tag {
+        request = request.tag_id(&tag);
+    }
+    request.send().await
+}
+
+async fn webhooks(
+    client: &UpClient,
+    output: Output,
+    command: WebhookCommand,
+) -> Result<(), String> {
+    let headers = ["ID", "URL", "DESCRIPTION", "CREATED"];
+    let row = |webhook: &Webhook| {
+        let attributes = &webhook.attributes;
+        vec![
+            webhook.id.to_string(),
+            attributes.url.to_string(),
+            attributes.description.clone().unwrap_or_default(),
+            attributes.created_at.to_rfc3339(),
+        ]
+    };
+    match command {
+        WebhookCommand::List => {
+            let webhooks = client.list_webhooks().send().await?;
+            print(
+                output,
+                &webhooks,
+                || webhooks.iter().map(row).collect(),
+                &headers,
+            )?;
+        }
+        WebhookCommand::Create { url, description } => {
+            let mut request = client.create_webhook(url);
+            if let Some(description) = description {
+                request = request.description(&description);
+            }
+            let webhook = request.send().await?;
+            print(
+                output,
+                &webhook,
+                || {
+                    let mut row = row(&webhook);
+                    row.push(webhook.attributes.secret_key.clone().unwrap_or_default());
+                    vec![row]
+                },
+                &["ID", "URL", "DESCRIPTION", "CREATED", "SECRET KEY"],
+            )?;
+        }
+        WebhookCommand::Get { id } => {
+            let webhook = client.retrieve_webhook(id).send().await?;
+            print(output, &webhook, || vec![row(&webhook)], &headers)?;
+        }
+        WebhookCommand::Delete { id } => client.delete_webhook(id).send().await?,
+        WebhookCommand::Ping { id } => {
+            let event = client.ping_webhook(id).send().await?;
+            print(
+                output,
+                &event,
+                || {
+                    vec![vec![
+                        event.id.to_string(),
+                        event.attributes.event_type.to_string(),
+                        event.attributes.created_at.to_rfc3339(),
+                    ]]
+                },
+                &["ID", "EVENT", "CREATED"],
+            )?;
+        }
+        WebhookCommand::Logs { id } => {
+            let logs = client.list_webhook_logs(id).send().await?;
+            print(
+                output,
+                &logs,
+                || {
+                    logs.iter()
+                        .map(|log| {
+                            let attributes = &log.attributes;
+                            vec![
+                                log.id.to_string(),
+                                attributes.created_at.to_rfc3339(),
+                                attributes.delivery_status.to_string(),
+                                attributes
+                                    .response
+                                    .as_ref()
+                                    .map(|response| response.status_code.to_string())
+                                    .unwrap_or_default(),
+                            ]
+                        })
+                        .collect()
+                },
+                &["ID", "CREATED", "STATUS", "RESPONSE"],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn transaction_row(transaction: &Transaction, timezone: Tz) -> Vec<String> {
+    let attributes = &transaction.attributes;
+    vec![
+        transaction.id.to_string(),
+        attributes
+            .created_at
+            .with_timezone(&timezone)
+            .format("%Y-%m-%d %H:%M")
+            .to_string(),
+        attributes.status.to_string(),
+        attributes.description.to_string(),
+        attributes.amount.value.to_string(),
+        transaction.category_id().cloned().unwrap_or_default(),
+    ]
+}
+
+fn transaction_details(transaction: &Transaction) -> Vec<Vec<String>> {
+    let attributes = &transaction.attributes;
+    let optional = |value: Option<&String>| value.cloned().unwrap_or_default();
+    let tags: Vec<&str> = transaction
+        .tag_ids()
+        .iter()
+        .map(|tag| tag.as_str())
+        .collect();
+    let fields = [
+        ("Id", transaction.id.to_string()),
+        ("Status", attributes.status.to_string()),
+        ("Description", attributes.description.to_string()),
+        ("Message", optional(attributes.message.as_ref())),
+        ("Raw text", optional(attributes.raw_text.as_ref())),
+        (
+            "Amount",
+            format!(
+                "{} {}",
+                attributes.amount.value, attributes.amount.currency_code
+            ),
+        ),
+        (
+            "Created",
+            attributes
+                .created_at
+                .with_timezone(&DEFAULT_TIMEZONE)
+                .to_rfc3339(),
+        ),
+        (
+            "Settled",
+            attributes
+                .settled_at
+                .map(|settled_at| settled_at.with_timezone(&DEFAULT_TIMEZONE).to_rfc3339())
+                .unwrap_or_default(),
+        ),
+        ("Account", transaction.account_id().to_string()),
+        (
+            "Transfer account",
+            optional(transaction.transfer_account_id()),
+        ),
+        ("Category", optional(transaction.category_id())),
+        (
+            "Parent category",
+            optional(transaction.parent_category_id()),
+        ),
+        ("Tags", tags.join(", ")),
+    ];
+    fields
+        .into_iter()
+        .map(|(field, value)| vec![field.to_string(), value])
+        .collect()
+}
+
+/// Prints `value` as JSON, or the rows from `rows` as a table under `headers`.
+fn print<T: Serialize>(
+    output: Output,
+    value: &T,
+    rows: impl FnOnce() -> Vec<Vec<String>>,
+    headers: &[&str],
+) -> Result<(), String> {
+    match output {
+        Output::Json => {
+            let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+            println!("{}", json);
+        }
+        Output::Table => print!("{}", table(headers, &rows())),
+    }
+    Ok(())
+}
+
+/// Lines up the rows in columns, padding each to its widest cell.
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        format!("{}\n", padded.join("  ").trim_end())
+    };
+    let mut table = line(headers.to_vec());
+    for row in rows {
+        table.push_str(&line(row.iter().map(|cell| cell.as_str()).collect()));
+    }
+    table
+}
+
+fn parse_timezone(timezone: &str) -> Result<Tz, String> {
+    timezone
+        .parse::<Tz>()
+        .map_err(|_| format!("{} is not a timezone, e.g. Australia/Sydney.", timezone))
+}
+
+fn parse_month(month: &str) -> Result<(i32, u32), String> {
+    let date = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .map_err(|_| format!("{} is not a month, e.g. 2022-10.", month))?;
+    Ok((
+        chrono::Datelike::year(&date),
+        chrono::Datelike::month(&date),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn parses_commands_and_prints_tables() {
+        Cli::command().debug_assert();
+        let cli = Cli::parse_from([
+            "uprs",
+            "transactions",
+            "--month",
+            "2022-10",
+            "--status",
+            "settled",
+            "--output",
+            "json",
+        ]);
+        assert_eq!(cli.output, Output::Json);
+        match cli.command {
+            Command::Transactions(filters) => {
+                assert_eq!(filters.month, Some((2022, 10)));
+                assert_eq!(filters.status, Some(StatusArg::Settled));
+            }
+            command => panic!("Parsed {:?}", command),
+        }
+        let conflicting = [
+            "uprs",
+            "transactions",
+            "--date",
+            "2022-10-01",
+            "--month",
+            "2022-10",
+        ];
+        assert!(Cli::try_parse_from(conflicting).is_err());
+        let too_many_days = ["uprs", "transactions", "--last-days", "4000000000"];
+        assert!(Cli::try_parse_from(too_many_days).is_err());
+        let cli = Cli::parse_from(["uprs", "ping", "--token-file", "token.txt"]);
+        assert_eq!(cli.token_file, Some(PathBuf::from("token.txt")));
+        let both = ["uprs", "ping", "--token-file", "-", "--token", "up:yeah:x"];
+        assert!(Cli::try_parse_from(both).is_err());
+
+        let rows = vec![vec!["1".to_string(), "Spending".to_string()]];
+        assert_eq!(table(&["ID", "NAME"], &rows), "ID  NAME\n1   Spending\n");
+    }
+}
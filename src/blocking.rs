@@ -0,0 +1,377 @@
+use crate::api_endpoints::*;
+use crate::cancel::Deadline;
+use crate::models::*;
+use crate::request_sender::{endpoint_request, meta_document, ApiEndpoint, ApiResponse};
+use crate::trace::Trace;
+use crate::transport::{HttpRequest, HttpResponse};
+use serde::de::DeserializeOwned;
+
+/// The synchronous counterpart of `request_sender::ApiRequest`, implemented by the same endpoint
+/// builders. Requests are sent through `HttpTransport::send_blocking` of the `UpClient`'s
+/// transport and are subject to its rate limit.
+pub trait ApiRequest: ApiEndpoint {
+    type T;
+    fn send(self) -> Result<Self::T, String>;
+    /// Sends the request and returns the whole response document, including `meta` and
+    /// `included` alongside `data`.
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String>;
+}
+
+pub(crate) struct RequestSender {}
+
+impl RequestSender {
+    pub(crate) fn send_paginate<T: DeserializeOwned, K: ApiEndpoint>(
+        base: K,
+    ) -> Result<Vec<T>, String> {
+        RequestSender::send_paginate_document::<T, K>(base).map(|document| document.data)
+    }
+
+    pub(crate) fn send_paginate_document<T: DeserializeOwned, K: ApiEndpoint>(
+        base: K,
+    ) -> Result<ApiResponse<Vec<T>>, String> {
+        let request = endpoint_request(&base);
+        let trace = Trace::request(&request);
+        let result = RequestSender::fetch_pages(&base, request, &trace);
+        trace.finish(&result, result.as_ref().map_or(0, |document| document.data.len()));
+        result
+    }
+
+    fn fetch_pages<T: DeserializeOwned, K: ApiEndpoint>(
+        base: &K,
+        request: HttpRequest,
+        trace: &Trace,
+    ) -> Result<ApiResponse<Vec<T>>, String> {
+        let deadline = Deadline::start(&base.get_client().get_timeouts());
+        let send = |request| RequestSender::send_within(base, request, deadline);
+        let mut rtn = RequestSender::fetch_page(&send, request, trace, 1)?;
+        let mut pages = 1;
+        while let Some(next) = rtn.next_link() {
+            pages += 1;
+            let request = HttpRequest::get(&next, base.get_token());
+            rtn.append_page(RequestSender::fetch_page(&send, request, trace, pages)?);
+        }
+        trace.pages(pages);
+        Ok(rtn)
+    }
+
+    fn fetch_page<T: DeserializeOwned>(
+        send: &dyn Fn(HttpRequest) -> Result<HttpResponse, String>,
+        request: HttpRequest,
+        trace: &Trace,
+        page: usize,
+    ) -> Result<ApiResponse<Vec<T>>, String> {
+        let page_trace = trace.page(&request, page);
+        let response = send(request);
+        trace.response(&response);
+        page_trace.response(&response);
+        let result: Result<ApiResponse<Vec<T>>, String> =
+            response.and_then(|response| response.parse());
+        page_trace.finish(&result, result.as_ref().map_or(0, |page| page.data.len()));
+        result
+    }
+
+    /// Sends the only request of an endpoint within its timeouts.
+    fn send_once<K: ApiEndpoint>(base: &K, request: HttpRequest) -> Result<HttpResponse, String> {
+        let deadline = Deadline::start(&base.get_client().get_timeouts());
+        RequestSender::send_within(base, request, deadline)
+    }
+
+    /// Sends a request once the rate limit allows it. The read timeout is shortened to the time
+    /// left before `deadline`.
+    fn send_within<K: ApiEndpoint>(
+        base: &K,
+        mut request: HttpRequest,
+        deadline: Option<Deadline>,
+    ) -> Result<HttpResponse, String> {
+        let _permit = base.get_client().permit_blocking();
+        request.timeouts = base.get_client().get_timeouts();
+        if let Some(deadline) = deadline {
+            let remaining = deadline.remaining()?;
+            let read = request.timeouts.read.map_or(remaining, |read| read.min(remaining));
+            request.timeouts.read = Some(read);
+        }
+        base.get_client().get_transport().send_blocking(request)
+    }
+
+    pub(crate) fn send<T: DeserializeOwned, K: ApiEndpoint>(base: K) -> Result<T, String> {
+        RequestSender::send_document::<T, K>(base).map(|document| document.data)
+    }
+
+    pub(crate) fn send_document<T: DeserializeOwned, K: ApiEndpoint>(
+        base: K,
+    ) -> Result<ApiResponse<T>, String> {
+        let request = endpoint_request(&base);
+        let trace = Trace::request(&request);
+        let response = RequestSender::send_once(&base, request);
+        trace.response(&response);
+        let result = response.and_then(|response| response.parse());
+        trace.finish(&result, 1);
+        result
+    }
+
+    pub(crate) fn send_no_content<K: ApiEndpoint>(base: K) -> Result<(), String> {
+        RequestSender::send_no_content_document::<K>(base).map(|document| document.data)
+    }
+
+    pub(crate) fn send_no_content_document<K: ApiEndpoint>(
+        base: K,
+    ) -> Result<ApiResponse<()>, String> {
+        let request = endpoint_request(&base);
+        let trace = Trace::request(&request);
+        let response = RequestSender::send_once(&base, request);
+        trace.response(&response);
+        let result = response.and_then(|response| response.no_content());
+        trace.finish(&result, 0);
+        result?;
+        Ok(ApiResponse {
+            data: (),
+            links: None,
+            meta: None,
+            included: None,
+        })
+    }
+
+    pub(crate) fn send_meta<T: DeserializeOwned, K: ApiEndpoint>(base: K) -> Result<T, String> {
+        RequestSender::send_meta_document::<T, K>(base).map(|document| document.data)
+    }
+
+    pub(crate) fn send_meta_document<T: DeserializeOwned, K: ApiEndpoint>(
+        base: K,
+    ) -> Result<ApiResponse<T>, String> {
+        let request = endpoint_request(&base);
+        let trace = Trace::request(&request);
+        let response = RequestSender::send_once(&base, request);
+        trace.response(&response);
+        let result = response.and_then(|response| meta_document(response.parse()?));
+        trace.finish(&result, 1);
+        result
+    }
+}
+
+impl ApiRequest for ListAccounts {
+    type T = Vec<Account>;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_paginate::<Account, ListAccounts>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<Account, ListAccounts>(self)
+    }
+}
+
+impl ApiRequest for RetrieveAccount {
+    type T = Account;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<Account, RetrieveAccount>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<Account, RetrieveAccount>(self)
+    }
+}
+
+impl ApiRequest for ListCategories {
+    type T = Vec<Category>;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_paginate::<Category, ListCategories>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<Category, ListCategories>(self)
+    }
+}
+
+impl ApiRequest for RetrieveCategory {
+    type T = Category;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<Category, RetrieveCategory>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<Category, RetrieveCategory>(self)
+    }
+}
+
+impl ApiRequest for ListTags {
+    type T = Vec<Tag>;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_paginate::<Tag, ListTags>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<Tag, ListTags>(self)
+    }
+}
+
+impl ApiRequest for ListTransactions {
+    type T = Vec<Transaction>;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_paginate::<Transaction, ListTransactions>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<Transaction, ListTransactions>(self)
+    }
+}
+
+impl ApiRequest for RetrieveTransaction {
+    type T = Transaction;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<Transaction, RetrieveTransaction>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<Transaction, RetrieveTransaction>(self)
+    }
+}
+
+impl ApiRequest for CategorizeTransaction {
+    type T = ();
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_no_content::<CategorizeTransaction>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_no_content_document::<CategorizeTransaction>(self)
+    }
+}
+
+impl ApiRequest for AddTagToTransaction {
+    type T = ();
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_no_content::<AddTagToTransaction>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_no_content_document::<AddTagToTransaction>(self)
+    }
+}
+
+impl ApiRequest for RemoveTagFromTransaction {
+    type T = ();
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_no_content::<RemoveTagFromTransaction>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_no_content_document::<RemoveTagFromTransaction>(self)
+    }
+}
+
+impl ApiRequest for Ping {
+    type T = PingMeta;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_meta::<PingMeta, Ping>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_meta_document::<PingMeta, Ping>(self)
+    }
+}
+
+impl ApiRequest for ListWebhooks {
+    type T = Vec<Webhook>;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_paginate::<Webhook, ListWebhooks>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<Webhook, ListWebhooks>(self)
+    }
+}
+
+impl ApiRequest for CreateWebhook {
+    type T = Webhook;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<Webhook, CreateWebhook>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<Webhook, CreateWebhook>(self)
+    }
+}
+
+impl ApiRequest for RetrieveWebhook {
+    type T = Webhook;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<Webhook, RetrieveWebhook>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<Webhook, RetrieveWebhook>(self)
+    }
+}
+
+impl ApiRequest for DeleteWebhook {
+    type T = ();
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_no_content::<DeleteWebhook>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_no_content_document::<DeleteWebhook>(self)
+    }
+}
+
+impl ApiRequest for PingWebhook {
+    type T = WebhookEvent;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send::<WebhookEvent, PingWebhook>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_document::<WebhookEvent, PingWebhook>(self)
+    }
+}
+
+impl ApiRequest for ListWebhookLogs {
+    type T = Vec<WebhookDeliveryLog>;
+
+    fn send(self) -> Result<Self::T, String> {
+        RequestSender::send_paginate::<WebhookDeliveryLog, ListWebhookLogs>(self)
+    }
+
+    fn send_document(self) -> Result<ApiResponse<Self::T>, String> {
+        RequestSender::send_paginate_document::<WebhookDeliveryLog, ListWebhookLogs>(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::blocking::*;
+    use crate::testing::{self, MockUpApi};
+    use crate::transport::Method;
+
+    #[test]
+    fn requests_go_through_the_transport() {
+        let mock = MockUpApi::new()
+            .account(testing::account("a", "Spending", AccountType::Transactional, 1050))
+            .account(testing::account("b", "Savings", AccountType::Saver, 0));
+        let client = mock.client();
+
+        let accounts = client.list_accounts().page_size(1).send().unwrap();
+        assert_eq!(accounts.len(), 2);
+        client.delete_webhook("missing".to_string()).send().unwrap_err();
+
+        let requests = mock.get_requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].url.contains("page%5Bafter%5D"));
+        assert_eq!(requests[2].method, Method::Delete);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::transport::Timeouts;
+use std::future::Future;
+use std::pin::Pin;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// Cancels the requests it is passed to, e.g. `ListTransactions::send_cancellable`.
+///
+/// Clones share the same state, so one clone can be handed to the task doing the fetch and another
+/// kept to cancel it.
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelState>,
+}
+
+/// The wakers of the requests waiting on the token, by the slot each request was given.
+#[derive(Default)]
+struct CancelState {
+    cancelled: AtomicBool,
+    next_slot: AtomicU64,
+    waiting: Mutex<HashMap<u64, Waker>>,
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// Cancels the requests using this token. Requests already in flight are dropped.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        let waiting = std::mem::take(&mut *self.waiting());
+        for waker in waiting.into_values() {
+            waker.wake();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn waiting(&self) -> std::sync::MutexGuard<'_, HashMap<u64, Waker>> {
+        self.inner.waiting.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn slot(&self) -> u64 {
+        self.inner.next_slot.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn register(&self, slot: u64, waker: &Waker) {
+        let mut waiting = self.waiting();
+        match waiting.get(&slot) {
+            Some(waiting) if waiting.will_wake(waker) => {}
+            _ => {
+                waiting.insert(slot, waker.clone());
+            }
+        }
+    }
+
+    fn unregister(&self, slot: u64) {
+        self.waiting().remove(&slot);
+    }
+}
+
+/// A paginated request that was cancelled or ran out of time, with the items of the pages that
+/// were fetched before it stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interrupted<T> {
+    pub items: Vec<T>,
+    pub error: String,
+}
+
+/// The point by which a logical request has to finish, from the `total` timeout.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Deadline {
+    at: Instant,
+    total: Duration,
+}
+
+impl Deadline {
+    pub(crate) fn start(timeouts: &Timeouts) -> Option<Deadline> {
+        timeouts.total.map(|total| Deadline {
+            at: Instant::now() + total,
+            total,
+        })
+    }
+
+    /// The time left, or an error once there is none.
+    pub(crate) fn remaining(&self) -> Result<Duration, String> {
+        match self.at.checked_duration_since(Instant::now()) {
+            Some(remaining) if !remaining.is_zero() => Ok(remaining),
+            _ => Err(self.error()),
+        }
+    }
+
+    fn error(&self) -> String {
+        format!("The request did not finish within {:?}.", self.total)
+    }
+}
+
+/// Runs `future` until it finishes, `deadline` passes or `cancel` is cancelled.
+pub(crate) async fn interruptible<F, R>(
+    future: F,
+    deadline: Option<Deadline>,
+    cancel: Option<&CancelToken>,
+) -> Result<R, String>
+where
+    F: Future<Output = Result<R, String>>,
+{
+    let timer = match deadline {
+        None => None,
+        Some(deadline) => Some((futures_timer::Delay::new(deadline.remaining()?), deadline)),
+    };
+    Interruptible {
+        future: Box::pin(future),
+        timer,
+        cancel: cancel.map(|cancel| (cancel, cancel.slot())),
+    }
+    .await
+}
+
+struct Interruptible<'a, F> {
+    future: Pin<Box<F>>,
+    timer: Option<(futures_timer::Delay, Deadline)>,
+    cancel: Option<(&'a CancelToken, u64)>,
+}
+
+/// Removes the waker from the token once the request has finished or been dropped, so a token
+/// that is never cancelled does not keep the wakers of every request it was used for.
+impl<F> Drop for Interruptible<'_, F> {
+    fn drop(&mut self) {
+        if let Some((cancel, slot)) = self.cancel {
+            cancel.unregister(slot);
+        }
+    }
+}
+
+impl<F, R> Future for Interruptible<'_, F>
+where
+    F: Future<Output = Result<R, String>>,
+{
+    type Output = Result<R, String>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Some((cancel, slot)) = self.cancel {
+            cancel.register(slot, cx.waker());
+            if cancel.is_cancelled() {
+                return Poll::Ready(Err("The request was cancelled.".to_string()));
+            }
+        }
+        if let Poll::Ready(result) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(result);
+        }
+        if let Some((timer, deadline)) = &mut self.timer {
+            if Pin::new(timer).poll(cx).is_ready() {
+                return Poll::Ready(Err(deadline.error()));
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cancel::*;
+    use crate::models::{AccountType, Tag};
+    use crate::request_sender::{ApiRequest, PaginatedRequest};
+    use crate::testing::{self, MockUpApi};
+    use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+    use async_trait::async_trait;
+
+    /// Answers the first page at once and then stalls.
+    struct Stalls {
+        mock: MockUpApi,
+    }
+
+    #[async_trait]
+    impl HttpTransport for Stalls {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+            if request.query.iter().any(|(name, _)| name == "page[after]")
+                || request.url.contains("page%5Bafter%5D")
+            {
+                futures_timer::Delay::new(Duration::from_secs(60)).await;
+            }
+            self.mock.send(request).await
+        }
+    }
+
+    fn stalling_mock() -> (MockUpApi, crate::client::UpClient) {
+        let mock = MockUpApi::new().tag("a").tag("b").tag("c");
+        let client = mock.client().transport(Stalls { mock: mock.clone() });
+        (mock, client)
+    }
+
+    #[tokio::test]
+    async fn total_timeout_returns_the_pages_fetched() {
+        let (_, client) = stalling_mock();
+        let client = client.timeouts(Timeouts::new().total(Duration::from_millis(50)));
+        let error = client.list_tags().page_size(2).send().await.unwrap_err();
+        assert!(error.contains("did not finish within 50ms"));
+
+        let interrupted = client
+            .list_tags()
+            .page_size(2)
+            .send_cancellable(&CancelToken::new())
+            .await
+            .unwrap_err();
+        let ids: Vec<String> = interrupted
+            .items
+            .iter()
+            .map(|tag: &Tag| tag.id.clone())
+            .collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn cancels_a_paginated_fetch() {
+        let (mock, client) = stalling_mock();
+        let mock = mock.account(testing::account("x", "X", AccountType::Saver, 1));
+        let cancel = CancelToken::new();
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            futures_timer::Delay::new(Duration::from_millis(20)).await;
+            canceller.cancel();
+        });
+        let interrupted = client
+            .list_tags()
+            .page_size(1)
+            .send_cancellable(&cancel)
+            .await
+            .unwrap_err();
+        assert_eq!(interrupted.error, "The request was cancelled.");
+        assert_eq!(interrupted.items.len(), 1);
+
+        let accounts = mock.client().list_accounts().send_cancellable(&cancel).await;
+        assert_eq!(accounts.unwrap_err().error, "The request was cancelled.");
+    }
+
+    #[tokio::test]
+    async fn forgets_finished_requests() {
+        let mock = MockUpApi::new().tag("a");
+        let cancel = CancelToken::new();
+        for _ in 0..20 {
+            let client = mock.client();
+            let cancel = cancel.clone();
+            tokio::spawn(async move { client.list_tags().send_cancellable(&cancel).await })
+                .await
+                .unwrap()
+                .unwrap();
+        }
+        assert!(cancel.waiting().is_empty());
+    }
+}
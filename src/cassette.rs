@@ -0,0 +1,416 @@
+use crate::transport::{HttpRequest, HttpResponse, HttpTransport, Method};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The fields of a response body that hold free text, where account numbers can turn up (e.g. the
+/// description of a transfer from another bank).
+const TEXT_FIELDS: [&str; 4] = ["description", "rawText", "message", "displayName"];
+
+/// Runs of at least this many digits in a text field are treated as account numbers.
+const ACCOUNT_NUMBER_DIGITS: usize = 6;
+
+/// A recording of request/response pairs, stored as JSON.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// A request without its headers, so the bearer token is never written to disk.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub url: String,
+    pub query: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Cassette {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Cassette, String> {
+        let json = fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("{}: {}", path.as_ref().display(), e))?;
+        serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path.as_ref(), json).map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+}
+
+impl From<RecordedResponse> for HttpResponse {
+    fn from(response: RecordedResponse) -> HttpResponse {
+        HttpResponse {
+            status: response.status,
+            headers: response.headers,
+            body: response.body,
+        }
+    }
+}
+
+/// A transport that sends requests through another transport and records every request/response
+/// pair into a cassette file, which is rewritten after each request.
+///
+/// Request headers are not recorded. The bearer token is also replaced wherever else it appears,
+/// and digit runs that look like account numbers are masked in the free-text fields of JSON
+/// bodies. Other values can be redacted with `redact`.
+///
+/// A failure to write the cassette is printed rather than returned, as the request itself was
+/// sent and answered.
+pub struct Recorder {
+    inner: Box<dyn HttpTransport>,
+    path: PathBuf,
+    redactor: Redactor,
+    cassette: Mutex<Cassette>,
+}
+
+/// The redactions applied to what is recorded, and to requests before they are matched against
+/// their recording.
+#[derive(Default)]
+struct Redactor {
+    redactions: Vec<(String, String)>,
+}
+
+impl Recorder {
+    pub fn new<P: AsRef<Path>, T: HttpTransport + 'static>(path: P, inner: T) -> Recorder {
+        Recorder {
+            inner: Box::new(inner),
+            path: path.as_ref().to_path_buf(),
+            redactor: Redactor::default(),
+            cassette: Mutex::new(Cassette::default()),
+        }
+    }
+
+    /// Replaces `secret` with `replacement` everywhere in the recording. The `Replayer` has to be
+    /// given the same redactions for the requests to match.
+    pub fn redact(mut self, secret: &str, replacement: &str) -> Recorder {
+        self.redactor.add(secret, replacement);
+        self
+    }
+
+    /// Adds an interaction to the cassette and rewrites the file.
+    fn save(&self, request: &HttpRequest, response: &HttpResponse) {
+        let interaction = self.record(request, response);
+        let mut cassette = self.cassette.lock().unwrap_or_else(|e| e.into_inner());
+        cassette.interactions.push(interaction);
+        if let Err(e) = cassette.save(&self.path) {
+            eprintln!("The cassette could not be saved: {}", e);
+        }
+    }
+
+    fn record(&self, request: &HttpRequest, response: &HttpResponse) -> Interaction {
+        let token = request.token.expose();
+        let redact = |text: &str| self.redactor.text(text, token);
+        Interaction {
+            request: self.redactor.request(request),
+            response: RecordedResponse {
+                status: response.status,
+                headers: response
+                    .headers
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), redact(value)))
+                    .collect(),
+                body: redact(&mask_account_numbers(&response.body)),
+            },
+        }
+    }
+}
+
+impl Redactor {
+    fn add(&mut self, secret: &str, replacement: &str) {
+        if !secret.is_empty() {
+            self.redactions
+                .push((secret.to_string(), replacement.to_string()));
+        }
+    }
+
+    fn text(&self, text: &str, token: &str) -> String {
+        let mut text = text.to_string();
+        if !token.is_empty() {
+            text = text.replace(token, "REDACTED");
+        }
+        for (secret, replacement) in &self.redactions {
+            text = text.replace(secret, replacement);
+        }
+        text
+    }
+
+    /// The request as it is recorded, with its headers left out.
+    fn request(&self, request: &HttpRequest) -> RecordedRequest {
+        let token = request.token.expose();
+        let redact = |text: &str| self.text(text, token);
+        RecordedRequest {
+            method: request.method,
+            url: redact(&request.url),
+            query: request
+                .query
+                .iter()
+                .map(|(name, value)| (redact(name), redact(value)))
+                .collect(),
+            body: request
+                .body
+                .as_ref()
+                .map(|body| redact(&mask_account_numbers(body))),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for Recorder {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let response = self.inner.send(request.clone()).await?;
+        self.save(&request, &response);
+        Ok(response)
+    }
+
+    #[cfg(feature = "blocking")]
+    fn send_blocking(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let response = self.inner.send_blocking(request.clone())?;
+        self.save(&request, &response);
+        Ok(response)
+    }
+}
+
+/// A transport that answers requests from a cassette without touching the network.
+///
+/// Each request is answered by the first interaction that has not been played yet with the same
+/// method, URL, query and body, once it has been redacted like the `Recorder` redacted it. A
+/// request with no such interaction fails with an error naming it.
+pub struct Replayer {
+    interactions: Vec<Interaction>,
+    played: Mutex<Vec<bool>>,
+    redactor: Redactor,
+}
+
+impl Replayer {
+    pub fn new(cassette: Cassette) -> Replayer {
+        Replayer {
+            played: Mutex::new(vec![false; cassette.interactions.len()]),
+            interactions: cassette.interactions,
+            redactor: Redactor::default(),
+        }
+    }
+
+    /// Redacts `secret` from requests before matching them, as `Recorder::redact` did when they
+    /// were recorded.
+    pub fn redact(mut self, secret: &str, replacement: &str) -> Replayer {
+        self.redactor.add(secret, replacement);
+        self
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replayer, String> {
+        Cassette::load(path).map(Replayer::new)
+    }
+
+    /// The interactions that have not been played yet.
+    pub fn unplayed(&self) -> Vec<Interaction> {
+        let played = self.played.lock().unwrap_or_else(|e| e.into_inner());
+        self.interactions
+            .iter()
+            .zip(played.iter())
+            .filter(|(_, played)| !**played)
+            .map(|(interaction, _)| interaction.clone())
+            .collect()
+    }
+
+    fn replay(&self, request: &HttpRequest) -> Result<HttpResponse, String> {
+        let recorded = self.redactor.request(request);
+        let mut played = self.played.lock().unwrap_or_else(|e| e.into_inner());
+        let position = self
+            .interactions
+            .iter()
+            .zip(played.iter())
+            .position(|(interaction, played)| !played && interaction.request == recorded);
+        match position {
+            Some(position) => {
+                played[position] = true;
+                Ok(self.interactions[position].response.clone().into())
+            }
+            None => Err(format!(
+                "No recorded interaction matches {:?} {} with query {:?} and body {:?}.",
+                request.method, request.url, request.query, request.body
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for Replayer {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        self.replay(&request)
+    }
+
+    #[cfg(feature = "blocking")]
+    fn send_blocking(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        self.replay(&request)
+    }
+}
+
+/// Masks account numbers in the free-text fields of a JSON body. Bodies that are not JSON are
+/// returned as they are.
+fn mask_account_numbers(body: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Err(_) => body.to_string(),
+        Ok(mut value) => {
+            mask_value(&mut value);
+            value.to_string()
+        }
+    }
+}
+
+fn mask_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Array(values) => values.iter_mut().for_each(mask_value),
+        serde_json::Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                match field {
+                    serde_json::Value::String(text) if TEXT_FIELDS.contains(&key.as_str()) => {
+                        *text = mask_digits(text);
+                    }
+                    _ => mask_value(field),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replaces each digit of a run of `ACCOUNT_NUMBER_DIGITS` or more digits with `X`. Spaces and
+/// dashes between digits count as part of the run, so `062-000 1234 5678` is masked whole.
+fn mask_digits(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut masked = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_digit() {
+            masked.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let mut end = i;
+        let mut digits = 0;
+        let mut j = i;
+        while j < chars.len() && (chars[j].is_ascii_digit() || chars[j] == ' ' || chars[j] == '-') {
+            if chars[j].is_ascii_digit() {
+                digits += 1;
+                end = j + 1;
+            }
+            j += 1;
+        }
+        for c in &chars[i..end] {
+            match digits >= ACCOUNT_NUMBER_DIGITS && c.is_ascii_digit() {
+                true => masked.push('X'),
+                false => masked.push(*c),
+            }
+        }
+        i = end;
+    }
+    masked
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cassette::*;
+    use crate::models::AccountType;
+    use crate::request_sender::ApiRequest;
+    use crate::testing::{self, MockUpApi};
+
+    fn cassette_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("uprs-{}-{}.json", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn records_and_replays() {
+        let path = cassette_path("records_and_replays");
+        let mock = MockUpApi::new()
+            .api_key(&"up:yeah:secret".to_string())
+            .account(testing::account(
+                "spending",
+                "Spending",
+                AccountType::Transactional,
+                100,
+            ))
+            .transaction(testing::transaction(
+                "t1",
+                "spending",
+                "Transfer from 062-000 12345678",
+                5000,
+                testing::at("2022-10-01T09:00:00+10:00"),
+            ));
+        let recording = mock.client().transport(Recorder::new(&path, mock.clone()));
+        let recorded = recording.list_transactions().send().await.unwrap();
+
+        let json = fs::read_to_string(&path).unwrap();
+        assert!(!json.contains("up:yeah:secret"));
+        assert!(!json.contains("12345678"));
+        assert!(json.contains("Transfer from XXX-XXX XXXXXXXX"));
+
+        let replayer = Replayer::load(&path).unwrap();
+        let replaying = mock.client().transport(replayer);
+        let replayed = replaying.list_transactions().send().await.unwrap();
+        assert_eq!(replayed, recorded);
+        assert!(replaying
+            .list_transactions()
+            .send()
+            .await
+            .unwrap_err()
+            .contains("No recorded interaction matches"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn replays_redacted_requests() {
+        let path = cassette_path("replays_redacted_requests");
+        let mock = MockUpApi::new().account(testing::account(
+            "123456789",
+            "Spending",
+            AccountType::Transactional,
+            100,
+        ));
+        let recorder = Recorder::new(&path, mock.clone()).redact("123456789", "spending");
+        let recording = mock.client().transport(recorder);
+        recording
+            .retrieve_account("123456789".to_string())
+            .send()
+            .await
+            .unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("123456789"));
+
+        let replayer = Replayer::load(&path)
+            .unwrap()
+            .redact("123456789", "spending");
+        let replaying = mock.client().transport(replayer);
+        let account = replaying
+            .retrieve_account("123456789".to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(account.id, "spending");
+        fs::remove_file(&path).unwrap();
+
+        let unwritable = path.join("missing").join("cassette.json");
+        let recording = mock.client().transport(Recorder::new(unwritable, mock.clone()));
+        assert!(recording.list_accounts().send().await.is_ok());
+    }
+
+    #[test]
+    fn masks_only_long_digit_runs() {
+        assert_eq!(mask_digits("Pizza 4 Hut 2022"), "Pizza 4 Hut 2022");
+        assert_eq!(mask_digits("To 12345678 ref 99"), "To XXXXXXXX ref 99");
+    }
+}
@@ -0,0 +1,256 @@
+use crate::models::{Category, Transaction};
+use std::collections::HashMap;
+
+/// The category hierarchy built from the flat list returned by `ListCategories`.
+///
+/// Categories whose parent is not in the list are treated as roots. Children keep the order in
+/// which they appeared in the list.
+pub struct CategoryTree {
+    categories: Vec<Category>,
+    index: HashMap<String, usize>,
+    children: HashMap<String, Vec<usize>>,
+    roots: Vec<usize>,
+}
+
+impl CategoryTree {
+    pub fn new(categories: Vec<Category>) -> CategoryTree {
+        let index: HashMap<String, usize> = categories
+            .iter()
+            .enumerate()
+            .map(|(i, category)| (category.id.to_string(), i))
+            .collect();
+        let mut children: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut roots: Vec<usize> = Vec::new();
+        for (i, category) in categories.iter().enumerate() {
+            match category.parent_id() {
+                Some(parent_id) if index.contains_key(parent_id) => {
+                    children.entry(parent_id.to_string()).or_default().push(i)
+                }
+                _ => roots.push(i),
+            }
+        }
+        CategoryTree {
+            categories,
+            index,
+            children,
+            roots,
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Category> {
+        self.index.get(id).map(|i| &self.categories[*i])
+    }
+
+    pub fn len(&self) -> usize {
+        self.categories.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.categories.is_empty()
+    }
+
+    /// The top level categories of the tree.
+    pub fn roots(&self) -> Vec<&Category> {
+        self.roots.iter().map(|i| &self.categories[*i]).collect()
+    }
+
+    pub fn parent(&self, id: &str) -> Option<&Category> {
+        self.get(id)
+            .and_then(|category| category.parent_id())
+            .and_then(|parent_id| self.get(parent_id))
+    }
+
+    pub fn children(&self, id: &str) -> Vec<&Category> {
+        match self.children.get(id) {
+            None => Vec::new(),
+            Some(children) => children.iter().map(|i| &self.categories[*i]).collect(),
+        }
+    }
+
+    /// The ancestors of a category in the tree, starting from its root.
+    pub fn ancestors(&self, id: &str) -> Vec<&Category> {
+        let mut ancestors: Vec<&Category> = Vec::new();
+        let mut current = self.parent(id);
+        while let Some(category) = current {
+            if category.id == id || ancestors.iter().any(|ancestor| ancestor.id == category.id) {
+                break;
+            }
+            ancestors.push(category);
+            current = self.parent(&category.id);
+        }
+        ancestors.reverse();
+        ancestors
+    }
+
+    /// The ids from the root down to the category, e.g. `good-life > hobbies`.
+    ///
+    /// A parent that is referenced but missing from the tree is still included.
+    pub fn path(&self, id: &str) -> Option<String> {
+        let category = self.get(id)?;
+        let mut ids: Vec<&str> = vec![&category.id];
+        let mut parent_id = category.parent_id();
+        while let Some(id) = parent_id {
+            if ids.contains(&id.as_str()) {
+                break;
+            }
+            ids.push(id);
+            parent_id = self.get(id).and_then(|parent| parent.parent_id());
+        }
+        ids.reverse();
+        Some(ids.join(" > "))
+    }
+
+    /// The names from the root down to the category, e.g. `Good Life > Hobbies`.
+    pub fn name_path(&self, id: &str) -> Option<String> {
+        let category = self.get(id)?;
+        let mut names: Vec<&str> = self
+            .ancestors(id)
+            .iter()
+            .map(|ancestor| ancestor.attributes.name.as_str())
+            .collect();
+        names.push(&category.attributes.name);
+        Some(names.join(" > "))
+    }
+
+    /// Iterates over the categories depth first, visiting each parent before its children.
+    pub fn iter(&self) -> impl Iterator<Item = &Category> {
+        let mut ordered: Vec<&Category> = Vec::with_capacity(self.categories.len());
+        let mut stack: Vec<usize> = self.roots.iter().rev().copied().collect();
+        while let Some(i) = stack.pop() {
+            let category = &self.categories[i];
+            ordered.push(category);
+            if let Some(children) = self.children.get(&category.id) {
+                stack.extend(children.iter().rev());
+            }
+        }
+        ordered.into_iter()
+    }
+
+    pub fn category_of(&self, transaction: &Transaction) -> Option<&Category> {
+        transaction.category_id().and_then(|id| self.get(id))
+    }
+
+    pub fn parent_category_of(&self, transaction: &Transaction) -> Option<&Category> {
+        transaction.parent_category_id().and_then(|id| self.get(id))
+    }
+
+    pub fn category_name(&self, transaction: &Transaction) -> Option<&String> {
+        self.category_of(transaction)
+            .map(|category| &category.attributes.name)
+    }
+
+    pub fn parent_category_name(&self, transaction: &Transaction) -> Option<&String> {
+        self.parent_category_of(transaction)
+            .map(|category| &category.attributes.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::category_tree::CategoryTree;
+    use crate::models::{Category, Transaction};
+    use serde_json::json;
+    use std::fs;
+
+    fn category(id: &str, name: &str, parent: Option<&str>) -> Category {
+        serde_json::from_value(json!({
+            "type": "categories",
+            "id": id,
+            "attributes": { "name": name },
+            "relationships": {
+                "parent": {
+                    "data": parent.map(|parent| json!({ "type": "categories", "id": parent }))
+                },
+                "children": { "data": [] }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn tree() -> CategoryTree {
+        CategoryTree::new(vec![
+            category("hobbies", "Hobbies", Some("good-life")),
+            category("home", "Home", None),
+            category("good-life", "Good Life", None),
+            category("groceries", "Groceries", Some("home")),
+            category(
+                "restaurants-and-cafes",
+                "Restaurants & Cafes",
+                Some("good-life"),
+            ),
+        ])
+    }
+
+    #[test]
+    fn navigation() {
+        let tree = tree();
+        assert_eq!(tree.len(), 5);
+        assert_eq!(tree.get("hobbies").unwrap().attributes.name, "Hobbies");
+        assert_eq!(tree.parent("hobbies").unwrap().id, "good-life");
+        assert!(tree.parent("good-life").is_none());
+        let children: Vec<&String> = tree
+            .children("good-life")
+            .iter()
+            .map(|category| &category.id)
+            .collect();
+        assert_eq!(children, vec!["hobbies", "restaurants-and-cafes"]);
+    }
+
+    #[test]
+    fn paths() {
+        let tree = tree();
+        assert_eq!(tree.path("hobbies").unwrap(), "good-life > hobbies");
+        assert_eq!(tree.name_path("hobbies").unwrap(), "Good Life > Hobbies");
+        assert_eq!(tree.path("home").unwrap(), "home");
+        assert!(tree.path("missing").is_none());
+    }
+
+    #[test]
+    fn tree_order() {
+        let ids: Vec<String> = tree()
+            .iter()
+            .map(|category| category.id.to_string())
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                "home",
+                "groceries",
+                "good-life",
+                "hobbies",
+                "restaurants-and-cafes"
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_parent_is_a_root() {
+        let json: String = fs::read_to_string("tests/example_json/list_categories.json").unwrap();
+        let categories: Vec<Category> = serde_json::from_value(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()["data"].take(),
+        )
+        .unwrap();
+        let tree = CategoryTree::new(categories);
+        assert_eq!(tree.roots().len(), 2);
+        assert_eq!(tree.path("hobbies").unwrap(), "good-life > hobbies");
+        assert_eq!(tree.name_path("hobbies").unwrap(), "Hobbies");
+    }
+
+    #[test]
+    fn resolves_transaction_categories() {
+        let json: String = fs::read_to_string("tests/example_json/list_transactions.json").unwrap();
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let relationships = &mut value["data"][0]["relationships"];
+        relationships["category"]["data"] = json!({ "type": "categories", "id": "hobbies" });
+        relationships["parentCategory"]["data"] =
+            json!({ "type": "categories", "id": "good-life" });
+        let transaction: Transaction = serde_json::from_value(value["data"][0].take()).unwrap();
+
+        let tree = tree();
+        assert_eq!(tree.category_name(&transaction).unwrap(), "Hobbies");
+        assert_eq!(
+            tree.parent_category_name(&transaction).unwrap(),
+            "Good Life"
+        );
+    }
+}
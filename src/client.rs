@@ -0,0 +1,181 @@
+use crate::api_endpoints::*;
+use crate::batch::Batch;
+use crate::rate_limit::{RateLimit, RateLimiter};
+use crate::token::Token;
+use crate::transport::{default_transport, HttpRequest, HttpResponse, HttpTransport, Timeouts};
+use std::sync::Arc;
+
+/// Holds the credentials and the HTTP transport used to talk to the API.
+///
+/// Cloning a client is cheap and the clones share the same token and transport. Endpoints created
+/// from a client (e.g. `client.list_accounts()`) send their requests through it, as do the
+/// relationship helpers on the models (e.g. `Account::transactions`).
+#[derive(Clone)]
+pub struct UpClient {
+    token: Arc<Token>,
+    transport: Arc<dyn HttpTransport>,
+    limiter: Option<Arc<RateLimiter>>,
+    timeouts: Timeouts,
+}
+
+impl UpClient {
+    pub fn new(api_key: &String) -> UpClient {
+        UpClient::with_token(Token::from(api_key))
+    }
+
+    pub fn with_token(token: Token) -> UpClient {
+        UpClient {
+            token: Arc::new(token),
+            transport: Arc::from(default_transport()),
+            limiter: None,
+            timeouts: Timeouts::default(),
+        }
+    }
+
+    /// Sends requests through `transport` instead of the default `reqwest` client.
+    pub fn transport<T: HttpTransport + 'static>(mut self, transport: T) -> UpClient {
+        self.transport = Arc::new(transport);
+        self
+    }
+
+    /// Throttles the requests of this client and its clones. Clones made before this call are not
+    /// affected.
+    pub fn rate_limit(mut self, limit: RateLimit) -> UpClient {
+        if limit.is_valid() {
+            self.limiter = Some(Arc::new(RateLimiter::new(limit)));
+        } else {
+            eprintln!("Requests per second has to be greater than 0.");
+        }
+        self
+    }
+
+    /// Sets the timeouts of the requests of this client. The endpoints can override them for a
+    /// single request with their own `timeouts`.
+    pub fn timeouts(mut self, timeouts: Timeouts) -> UpClient {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn get_token(&self) -> &Token {
+        &self.token
+    }
+
+    pub fn get_transport(&self) -> &dyn HttpTransport {
+        self.transport.as_ref()
+    }
+
+    pub fn get_timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+
+    /// Sends a request through the transport once the rate limit allows it.
+    pub(crate) async fn send(&self, mut request: HttpRequest) -> Result<HttpResponse, String> {
+        request.timeouts = self.timeouts;
+        let _permit = match &self.limiter {
+            None => None,
+            Some(limiter) => Some(limiter.acquire().await),
+        };
+        self.transport.send(request).await
+    }
+
+    /// Waits until the rate limit allows a request to be sent with the blocking client.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn permit_blocking(&self) -> Option<crate::rate_limit::Permit> {
+        self.limiter.as_ref().map(|limiter| limiter.acquire_blocking())
+    }
+
+    pub fn ping(&self) -> Ping {
+        Ping::with_client(self)
+    }
+
+    pub fn list_accounts(&self) -> ListAccounts {
+        ListAccounts::with_client(self)
+    }
+
+    pub fn retrieve_account(&self, account_id: String) -> RetrieveAccount {
+        RetrieveAccount::with_client(self, account_id)
+    }
+
+    pub fn retrieve_accounts(&self, account_ids: Vec<String>) -> Batch<RetrieveAccount> {
+        RetrieveAccount::batch(self, account_ids)
+    }
+
+    pub fn list_categories(&self) -> ListCategories {
+        ListCategories::with_client(self)
+    }
+
+    pub fn retrieve_category(&self, category_id: String) -> RetrieveCategory {
+        RetrieveCategory::with_client(self, category_id)
+    }
+
+    pub fn retrieve_categories(&self, category_ids: Vec<String>) -> Batch<RetrieveCategory> {
+        RetrieveCategory::batch(self, category_ids)
+    }
+
+    pub fn list_tags(&self) -> ListTags {
+        ListTags::with_client(self)
+    }
+
+    pub fn list_transactions(&self) -> ListTransactions {
+        ListTransactions::with_client(self)
+    }
+
+    pub fn retrieve_transaction(&self, transaction_id: String) -> RetrieveTransaction {
+        RetrieveTransaction::with_client(self, transaction_id)
+    }
+
+    pub fn retrieve_transactions(
+        &self,
+        transaction_ids: Vec<String>,
+    ) -> Batch<RetrieveTransaction> {
+        RetrieveTransaction::batch(self, transaction_ids)
+    }
+
+    pub fn categorize_transaction(
+        &self,
+        transaction_id: String,
+        category_id: Option<String>,
+    ) -> CategorizeTransaction {
+        CategorizeTransaction::with_client(self, transaction_id, category_id)
+    }
+
+    pub fn add_tag_to_transaction(
+        &self,
+        transaction_id: String,
+        tags: Vec<String>,
+    ) -> AddTagToTransaction {
+        AddTagToTransaction::with_client(self, transaction_id, tags)
+    }
+
+    pub fn remove_tag_from_transaction(
+        &self,
+        transaction_id: String,
+        tags: Vec<String>,
+    ) -> RemoveTagFromTransaction {
+        RemoveTagFromTransaction::with_client(self, transaction_id, tags)
+    }
+
+    pub fn list_webhooks(&self) -> ListWebhooks {
+        ListWebhooks::with_client(self)
+    }
+
+    pub fn create_webhook(&self, webhook_url: String) -> CreateWebhook {
+        CreateWebhook::with_client(self, webhook_url)
+    }
+
+    pub fn retrieve_webhook(&self, webhook_id: String) -> RetrieveWebhook {
+        RetrieveWebhook::with_client(self, webhook_id)
+    }
+
+    pub fn delete_webhook(&self, webhook_id: String) -> DeleteWebhook {
+        DeleteWebhook::with_client(self, webhook_id)
+    }
+
+    pub fn ping_webhook(&self, webhook_id: String) -> PingWebhook {
+        PingWebhook::with_client(self, webhook_id)
+    }
+
+    pub fn list_webhook_logs(&self, webhook_id: String) -> ListWebhookLogs {
+        ListWebhookLogs::with_client(self, webhook_id)
+    }
+}
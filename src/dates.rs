@@ -0,0 +1,155 @@
+use chrono::{DateTime, Days, Duration, FixedOffset, LocalResult, NaiveDate, TimeZone};
+pub use chrono_tz::Tz;
+
+/// The timezone dates are interpreted in unless another one is given.
+pub const DEFAULT_TIMEZONE: Tz = Tz::Australia__Melbourne;
+
+/// The first instant of `date` in `timezone`.
+///
+/// If midnight falls in a daylight saving gap, the first valid instant after it is used instead.
+pub fn start_of_day(date: NaiveDate, timezone: Tz) -> DateTime<FixedOffset> {
+    let mut local = date.and_hms_opt(0, 0, 0).unwrap();
+    loop {
+        match timezone.from_local_datetime(&local) {
+            LocalResult::Single(date_time) => return date_time.fixed_offset(),
+            LocalResult::Ambiguous(earliest, _) => return earliest.fixed_offset(),
+            LocalResult::None => local += Duration::minutes(15),
+        }
+    }
+}
+
+/// The start of `first` and the start of the day after `last`, so that every instant on the
+/// days from `first` to `last` inclusive falls within the bounds, or `None` if `last` is the last
+/// date `chrono` can represent.
+pub fn day_bounds(
+    first: NaiveDate,
+    last: NaiveDate,
+    timezone: Tz,
+) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    Some((
+        start_of_day(first, timezone),
+        start_of_day(last.succ_opt()?, timezone),
+    ))
+}
+
+/// The bounds of a calendar month, or `None` if `month` is not between 1 and 12.
+pub fn month_bounds(
+    year: i32,
+    month: u32,
+    timezone: Tz,
+) -> Option<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+    let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+    let next = match month {
+        12 => NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+        _ => NaiveDate::from_ymd_opt(year, month + 1, 1)?,
+    };
+    Some((start_of_day(first, timezone), start_of_day(next, timezone)))
+}
+
+/// The current date in `timezone`.
+pub fn today(timezone: Tz) -> NaiveDate {
+    chrono::Utc::now().with_timezone(&timezone).date_naive()
+}
+
+/// The first day of the `days` days ending on (and including) `today`, or the first date `chrono`
+/// can represent if there are not that many days before `today`.
+pub(crate) fn first_of_last_n_days(today: NaiveDate, days: u32) -> NaiveDate {
+    today
+        .checked_sub_days(Days::new(u64::from(days.max(1)) - 1))
+        .unwrap_or(NaiveDate::MIN)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dates::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn start_of_day_uses_standard_and_daylight_time() {
+        assert_eq!(
+            start_of_day(date(2022, 7, 1), DEFAULT_TIMEZONE).to_rfc3339(),
+            "2022-07-01T00:00:00+10:00"
+        );
+        assert_eq!(
+            start_of_day(date(2022, 12, 1), DEFAULT_TIMEZONE).to_rfc3339(),
+            "2022-12-01T00:00:00+11:00"
+        );
+    }
+
+    #[test]
+    fn day_bounds_across_daylight_saving_start() {
+        // Clocks in Melbourne went forward at 2am on 2 October 2022, so that day is 23 hours long.
+        let (since, until) =
+            day_bounds(date(2022, 10, 2), date(2022, 10, 2), DEFAULT_TIMEZONE).unwrap();
+        assert_eq!(since.to_rfc3339(), "2022-10-02T00:00:00+10:00");
+        assert_eq!(until.to_rfc3339(), "2022-10-03T00:00:00+11:00");
+        assert_eq!(until - since, Duration::hours(23));
+    }
+
+    #[test]
+    fn day_bounds_across_daylight_saving_end() {
+        // Clocks in Melbourne went back at 3am on 3 April 2022, so that day is 25 hours long.
+        let (since, until) =
+            day_bounds(date(2022, 4, 3), date(2022, 4, 3), DEFAULT_TIMEZONE).unwrap();
+        assert_eq!(since.to_rfc3339(), "2022-04-03T00:00:00+11:00");
+        assert_eq!(until.to_rfc3339(), "2022-04-04T00:00:00+10:00");
+        assert_eq!(until - since, Duration::hours(25));
+    }
+
+    #[test]
+    fn month_bounds_span_daylight_saving() {
+        let (since, until) = month_bounds(2022, 10, DEFAULT_TIMEZONE).unwrap();
+        assert_eq!(since.to_rfc3339(), "2022-10-01T00:00:00+10:00");
+        assert_eq!(until.to_rfc3339(), "2022-11-01T00:00:00+11:00");
+
+        let (since, until) = month_bounds(2022, 12, DEFAULT_TIMEZONE).unwrap();
+        assert_eq!(since.to_rfc3339(), "2022-12-01T00:00:00+11:00");
+        assert_eq!(until.to_rfc3339(), "2023-01-01T00:00:00+11:00");
+
+        assert!(month_bounds(2022, 13, DEFAULT_TIMEZONE).is_none());
+    }
+
+    #[test]
+    fn other_timezones() {
+        // Brisbane does not observe daylight saving.
+        let (since, until) = day_bounds(
+            date(2022, 10, 2),
+            date(2022, 10, 2),
+            Tz::Australia__Brisbane,
+        )
+        .unwrap();
+        assert_eq!(since.to_rfc3339(), "2022-10-02T00:00:00+10:00");
+        assert_eq!(until.to_rfc3339(), "2022-10-03T00:00:00+10:00");
+
+        // Midnight did not exist in Santiago on 3 September 2023.
+        assert_eq!(
+            start_of_day(date(2023, 9, 3), Tz::America__Santiago).to_rfc3339(),
+            "2023-09-03T01:00:00-03:00"
+        );
+    }
+
+    #[test]
+    fn last_n_days_includes_today() {
+        assert_eq!(
+            first_of_last_n_days(date(2022, 10, 3), 1),
+            date(2022, 10, 3)
+        );
+        assert_eq!(
+            first_of_last_n_days(date(2022, 10, 3), 7),
+            date(2022, 9, 27)
+        );
+        assert_eq!(
+            first_of_last_n_days(date(2022, 10, 3), u32::MAX),
+            NaiveDate::MIN
+        );
+        start_of_day(NaiveDate::MIN, DEFAULT_TIMEZONE);
+    }
+
+    #[test]
+    fn day_bounds_of_the_last_date() {
+        assert!(day_bounds(date(2022, 10, 3), NaiveDate::MAX, DEFAULT_TIMEZONE).is_none());
+    }
+}
@@ -0,0 +1,8 @@
+/// Contains `CsvWriter`, which writes transactions as CSV with a choice of columns.
+pub mod csv;
+/// Contains `LedgerWriter`, which writes transactions as a ledger, hledger or beancount journal.
+pub mod ledger;
+/// Contains `OfxWriter`, which writes the transactions of an account as an OFX bank statement.
+pub mod ofx;
+/// Contains `QifWriter`, which writes the transactions of an account as QIF.
+pub mod qif;
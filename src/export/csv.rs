@@ -0,0 +1,366 @@
+use crate::category_tree::CategoryTree;
+use crate::dates::{Tz, DEFAULT_TIMEZONE};
+use crate::models::{Account, Transaction};
+use chrono::format::{Item, StrftimeItems};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// A column of the CSV written by `CsvWriter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Column {
+    /// The id of the transaction, which `reconcile` uses to match rows to transactions.
+    Id,
+    /// When the transaction was created, in the timezone of the writer.
+    Date,
+    Description,
+    RawText,
+    /// The amount in the currency of the account, e.g. `-12.34`.
+    Amount,
+    Currency,
+    /// The amount in the currency of a foreign purchase.
+    ForeignAmount,
+    ForeignCurrency,
+    Category,
+    ParentCategory,
+    /// The tags of the transaction, separated by commas.
+    Tags,
+    AccountName,
+    Status,
+    CardSuffix,
+    /// The amount rounded up into a saver, e.g. `-0.66`.
+    RoundUp,
+}
+
+impl Column {
+    /// Every column, in the order used by default.
+    pub const ALL: [Column; 15] = [
+        Column::Id,
+        Column::Date,
+        Column::Description,
+        Column::RawText,
+        Column::Amount,
+        Column::Currency,
+        Column::ForeignAmount,
+        Column::ForeignCurrency,
+        Column::Category,
+        Column::ParentCategory,
+        Column::Tags,
+        Column::AccountName,
+        Column::Status,
+        Column::CardSuffix,
+        Column::RoundUp,
+    ];
+
+    pub fn header(&self) -> &'static str {
+        match self {
+            Column::Id => "Id",
+            Column::Date => "Date",
+            Column::Description => "Description",
+            Column::RawText => "Raw Text",
+            Column::Amount => "Amount",
+            Column::Currency => "Currency",
+            Column::ForeignAmount => "Foreign Amount",
+            Column::ForeignCurrency => "Foreign Currency",
+            Column::Category => "Category",
+            Column::ParentCategory => "Parent Category",
+            Column::Tags => "Tags",
+            Column::AccountName => "Account",
+            Column::Status => "Status",
+            Column::CardSuffix => "Card",
+            Column::RoundUp => "Round Up",
+        }
+    }
+}
+
+/// Writes transactions as CSV, one row per transaction after a header row.
+///
+/// Categories and accounts are written by name when a `CategoryTree` and the accounts are given,
+/// and by id otherwise.
+pub struct CsvWriter<'a> {
+    columns: Vec<Column>,
+    timezone: Tz,
+    date_format: String,
+    categories: Option<&'a CategoryTree>,
+    account_names: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> CsvWriter<'a> {
+    /// A writer with every column, writing dates as `2022-10-01` in Australia/Melbourne.
+    pub fn new() -> CsvWriter<'a> {
+        CsvWriter {
+            columns: Column::ALL.to_vec(),
+            timezone: DEFAULT_TIMEZONE,
+            date_format: "%Y-%m-%d".to_string(),
+            categories: None,
+            account_names: HashMap::new(),
+        }
+    }
+
+    pub fn columns(mut self, columns: &[Column]) -> CsvWriter<'a> {
+        if columns.is_empty() {
+            eprintln!("At least one column has to be selected.");
+        } else {
+            self.columns = columns.to_vec();
+        }
+        self
+    }
+
+    pub fn timezone(mut self, timezone: Tz) -> CsvWriter<'a> {
+        self.timezone = timezone;
+        self
+    }
+
+    /// A `chrono` format string for the `Date` column, e.g. `%d/%m/%Y %H:%M`.
+    pub fn date_format(mut self, date_format: &str) -> CsvWriter<'a> {
+        if is_valid_date_format(date_format) {
+            self.date_format = date_format.to_string();
+        } else {
+            eprintln!("{} is not a valid date format.", date_format);
+        }
+        self
+    }
+
+    pub fn categories(mut self, categories: &'a CategoryTree) -> CsvWriter<'a> {
+        self.categories = Some(categories);
+        self
+    }
+
+    pub fn accounts(mut self, accounts: &'a [Account]) -> CsvWriter<'a> {
+        self.account_names = accounts
+            .iter()
+            .map(|account| {
+                (
+                    account.id.as_str(),
+                    account.attributes.display_name.as_str(),
+                )
+            })
+            .collect();
+        self
+    }
+
+    pub fn write<W: Write>(
+        &self,
+        mut writer: W,
+        transactions: &[Transaction],
+    ) -> Result<(), String> {
+        let header: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| column.header().to_string())
+            .collect();
+        write_row(&mut writer, &header)?;
+        for transaction in transactions {
+            let row: Vec<String> = self
+                .columns
+                .iter()
+                .map(|column| self.value(*column, transaction))
+                .collect();
+            write_row(&mut writer, &row)?;
+        }
+        writer.flush().map_err(|e| e.to_string())
+    }
+
+    pub fn to_csv(&self, transactions: &[Transaction]) -> String {
+        let mut csv = Vec::new();
+        // Writing to a Vec can not fail.
+        self.write(&mut csv, transactions).unwrap();
+        String::from_utf8(csv).unwrap()
+    }
+
+    fn value(&self, column: Column, transaction: &Transaction) -> String {
+        let attributes = &transaction.attributes;
+        let category = |id: Option<&String>| match (id, self.categories) {
+            (None, _) => String::new(),
+            (Some(id), None) => id.to_string(),
+            (Some(id), Some(tree)) => match tree.get(id) {
+                None => id.to_string(),
+                Some(category) => category.attributes.name.to_string(),
+            },
+        };
+        match column {
+            Column::Id => transaction.id.to_string(),
+            Column::Date => attributes
+                .created_at
+                .with_timezone(&self.timezone)
+                .format(&self.date_format)
+                .to_string(),
+            Column::Description => attributes.description.to_string(),
+            Column::RawText => attributes.raw_text.clone().unwrap_or_default(),
+            Column::Amount => attributes.amount.value.to_string(),
+            Column::Currency => attributes.amount.currency_code.to_string(),
+            Column::ForeignAmount => match &attributes.foreign_amount {
+                None => String::new(),
+                Some(money) => money.value.to_string(),
+            },
+            Column::ForeignCurrency => match &attributes.foreign_amount {
+                None => String::new(),
+                Some(money) => money.currency_code.to_string(),
+            },
+            Column::Category => category(transaction.category_id()),
+            Column::ParentCategory => category(transaction.parent_category_id()),
+            Column::Tags => {
+                let tags: Vec<&str> = transaction.tag_ids().iter().map(|t| t.as_str()).collect();
+                tags.join(", ")
+            }
+            Column::AccountName => {
+                let account_id = transaction.account_id().as_str();
+                match self.account_names.get(account_id) {
+                    None => account_id.to_string(),
+                    Some(name) => name.to_string(),
+                }
+            }
+            Column::Status => attributes.status.to_string(),
+            Column::CardSuffix => attributes
+                .card_purchase_method
+                .as_ref()
+                .and_then(|method| method.card_number_suffix.clone())
+                .unwrap_or_default(),
+            Column::RoundUp => match &attributes.round_up {
+                None => String::new(),
+                Some(round_up) => round_up.amount.value.to_string(),
+            },
+        }
+    }
+}
+
+impl Default for CsvWriter<'_> {
+    fn default() -> Self {
+        CsvWriter::new()
+    }
+}
+
+/// Whether `chrono` can format a date with `date_format`, which it would otherwise panic on.
+pub(crate) fn is_valid_date_format(date_format: &str) -> bool {
+    StrftimeItems::new(date_format).all(|item| item != Item::Error)
+}
+
+fn write_row<W: Write>(writer: &mut W, values: &[String]) -> Result<(), String> {
+    let fields: Vec<String> = values.iter().map(|value| quote(value)).collect();
+    writeln!(writer, "{}", fields.join(",")).map_err(|e| e.to_string())
+}
+
+/// Quotes a field if it contains a comma, a quote or a line break, doubling any quotes.
+pub(crate) fn quote(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Splits CSV into records of fields, undoing the quoting of `quote`.
+pub(crate) fn parse(csv: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("The CSV ends inside a quoted field.".to_string());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::category_tree::CategoryTree;
+    use crate::export::csv::*;
+    use crate::models::{CardPurchaseMethod, CardPurchaseMethodType, RoundUp};
+    use crate::testing;
+
+    #[test]
+    fn writes_the_selected_columns() {
+        let mut pizza = testing::transaction(
+            "t1",
+            "spending",
+            "Pizza, \"Hut\"",
+            -2345,
+            testing::at("2022-10-01T23:30:00+00:00"),
+        );
+        pizza.relationships.category.data = Some(testing::identifier("categories", "takeaway"));
+        pizza.relationships.parent_category.data =
+            Some(testing::identifier("categories", "good-life"));
+        pizza.relationships.tags.data = vec![
+            testing::identifier("tags", "Pizza Night"),
+            testing::identifier("tags", "Friends"),
+        ];
+        pizza.attributes.card_purchase_method = Some(CardPurchaseMethod {
+            method: CardPurchaseMethodType::Contactless,
+            card_number_suffix: Some("0001".to_string()),
+        });
+        pizza.attributes.round_up = Some(RoundUp {
+            amount: testing::money(-55),
+            boost_portion: None,
+        });
+        let tree = CategoryTree::new(vec![
+            testing::category("good-life", "Good Life", None),
+            testing::category("takeaway", "TakeAway", Some("good-life")),
+        ]);
+        let accounts = vec![testing::account(
+            "spending",
+            "Spending",
+            crate::models::AccountType::Transactional,
+            0,
+        )];
+
+        let csv = CsvWriter::new()
+            .columns(&[
+                Column::Date,
+                Column::Description,
+                Column::Amount,
+                Column::Category,
+                Column::ParentCategory,
+                Column::Tags,
+                Column::AccountName,
+                Column::CardSuffix,
+                Column::RoundUp,
+            ])
+            .categories(&tree)
+            .accounts(&accounts)
+            .to_csv(&[pizza.clone()]);
+        assert_eq!(
+            csv,
+            "Date,Description,Amount,Category,Parent Category,Tags,Account,Card,Round Up\n\
+             2022-10-02,\"Pizza, \"\"Hut\"\"\",-23.45,TakeAway,Good Life,\"Pizza Night, Friends\",\
+             Spending,0001,-0.55\n"
+        );
+
+        let csv = CsvWriter::new()
+            .columns(&[Column::Date, Column::Category, Column::AccountName])
+            .timezone(chrono_tz::UTC)
+            .date_format("%d/%m/%Y %H:%M")
+            .to_csv(&[pizza.clone()]);
+        assert_eq!(
+            csv,
+            "Date,Category,Account\n01/10/2022 23:30,takeaway,spending\n"
+        );
+
+        let csv = CsvWriter::new()
+            .columns(&[Column::Date])
+            .date_format("%Q")
+            .to_csv(&[pizza]);
+        assert_eq!(csv, "Date\n2022-10-02\n");
+    }
+}
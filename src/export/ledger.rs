@@ -0,0 +1,637 @@
+use crate::category_tree::CategoryTree;
+use crate::dates::{self, Tz, DEFAULT_TIMEZONE};
+use crate::models::{Account, Transaction, TransactionStatus};
+use chrono::{Days, NaiveDate};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Write;
+
+/// The ledger account round-ups go to when their saver is not known.
+const ROUND_UPS: &str = "Assets:Up:Round-Ups";
+
+/// The plain-text accounting tool a journal is written for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+/// Writes transactions as a ledger, hledger or beancount journal of balanced entries.
+///
+/// Up accounts become `Assets:Up:<Name>` and categories `Expenses:<Parent>:<Child>` unless mapped
+/// with `account` and `category`. A transfer between Up accounts, which appears on both accounts,
+/// is written once as a two-leg entry from the account it was sent from, as is the round-up of a
+/// purchase. The receiving side is never written, so the transactions of the sending account have
+/// to be included for a transfer to appear, but the two sides can be written in separate appends.
+/// Held transactions are written as pending, as the balances of the accounts include them.
+///
+/// Every entry carries the id of its transaction as `up_id` metadata (and the id of the other
+/// side of a transfer as `up_counterpart_id`), so `journal_ids` and `skip` can be used to append
+/// only the transactions a journal does not have yet.
+///
+/// Beancount journals start with `open` directives for the accounts they use, dated at their
+/// first entry. When appending, `journal_accounts` and `opened` leave out those already opened.
+pub struct LedgerWriter<'a> {
+    dialect: Dialect,
+    timezone: Tz,
+    include_held: bool,
+    accounts: Vec<&'a Account>,
+    account_names: HashMap<String, String>,
+    categories: Option<&'a CategoryTree>,
+    category_accounts: HashMap<String, String>,
+    uncategorized_expenses: String,
+    uncategorized_income: String,
+    round_ups: Option<String>,
+    balance_assertions: bool,
+    as_of: Option<NaiveDate>,
+    skip: HashSet<String>,
+    opened: HashSet<String>,
+}
+
+/// A balanced entry, with the amounts of its postings in base units.
+struct Entry {
+    date: NaiveDate,
+    pending: bool,
+    description: String,
+    ids: Vec<(&'static str, String)>,
+    postings: Vec<(String, i128)>,
+    currency: String,
+}
+
+impl<'a> LedgerWriter<'a> {
+    /// A writer that dates entries in Australia/Melbourne and writes balance assertions.
+    pub fn new(dialect: Dialect) -> LedgerWriter<'a> {
+        LedgerWriter {
+            dialect,
+            timezone: DEFAULT_TIMEZONE,
+            include_held: true,
+            accounts: Vec::new(),
+            account_names: HashMap::new(),
+            categories: None,
+            category_accounts: HashMap::new(),
+            uncategorized_expenses: "Expenses:Uncategorized".to_string(),
+            uncategorized_income: "Income:Uncategorized".to_string(),
+            round_ups: None,
+            balance_assertions: true,
+            as_of: None,
+            skip: HashSet::new(),
+            opened: HashSet::new(),
+        }
+    }
+
+    pub fn timezone(mut self, timezone: Tz) -> LedgerWriter<'a> {
+        self.timezone = timezone;
+        self
+    }
+
+    pub fn include_held(mut self, include_held: bool) -> LedgerWriter<'a> {
+        self.include_held = include_held;
+        self
+    }
+
+    /// The Up accounts, used to name the ledger accounts and for the balance assertions.
+    pub fn accounts(mut self, accounts: &'a [Account]) -> LedgerWriter<'a> {
+        self.accounts = accounts.iter().collect();
+        self
+    }
+
+    /// Maps an Up account to a ledger account, e.g. `Assets:Bank:Up:Spending`.
+    pub fn account(mut self, account_id: &str, ledger_account: &str) -> LedgerWriter<'a> {
+        self.account_names
+            .insert(account_id.to_string(), ledger_account.to_string());
+        self
+    }
+
+    /// Names the expense accounts of unmapped categories after the categories and their parents.
+    pub fn categories(mut self, categories: &'a CategoryTree) -> LedgerWriter<'a> {
+        self.categories = Some(categories);
+        self
+    }
+
+    /// Maps a category to a ledger account, e.g. `Expenses:Food:Takeaway`.
+    pub fn category(mut self, category_id: &str, ledger_account: &str) -> LedgerWriter<'a> {
+        self.category_accounts
+            .insert(category_id.to_string(), ledger_account.to_string());
+        self
+    }
+
+    /// The accounts of uncategorized money going out and coming in.
+    pub fn uncategorized(mut self, expenses: &str, income: &str) -> LedgerWriter<'a> {
+        self.uncategorized_expenses = expenses.to_string();
+        self.uncategorized_income = income.to_string();
+        self
+    }
+
+    /// The Up account that receives round-ups, for when its side of them is not written.
+    pub fn round_ups(mut self, account_id: &str) -> LedgerWriter<'a> {
+        self.round_ups = Some(account_id.to_string());
+        self
+    }
+
+    pub fn balance_assertions(mut self, balance_assertions: bool) -> LedgerWriter<'a> {
+        self.balance_assertions = balance_assertions;
+        self
+    }
+
+    /// The day the balances of the accounts were fetched. Defaults to today.
+    pub fn as_of(mut self, as_of: NaiveDate) -> LedgerWriter<'a> {
+        self.as_of = Some(as_of);
+        self
+    }
+
+    /// Leaves out the entries of these transactions, e.g. the `journal_ids` of a journal.
+    pub fn skip(mut self, transaction_ids: &HashSet<String>) -> LedgerWriter<'a> {
+        self.skip = transaction_ids.clone();
+        self
+    }
+
+    /// Leaves out the beancount `open` directives of these accounts, e.g. the `journal_accounts`
+    /// of a journal.
+    pub fn opened(mut self, accounts: &HashSet<String>) -> LedgerWriter<'a> {
+        self.opened = accounts.clone();
+        self
+    }
+
+    pub fn write<W: Write>(
+        &self,
+        mut writer: W,
+        transactions: &[Transaction],
+    ) -> Result<(), String> {
+        writer
+            .write_all(self.to_journal(transactions).as_bytes())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn to_journal(&self, transactions: &[Transaction]) -> String {
+        let entries = self.entries(transactions);
+        let as_of = self.as_of.unwrap_or_else(|| dates::today(self.timezone));
+        let mut journal = String::new();
+        if self.dialect == Dialect::Beancount {
+            self.write_opens(&mut journal, &entries, as_of);
+        }
+        for entry in &entries {
+            self.write_entry(&mut journal, entry);
+        }
+        if self.balance_assertions {
+            for account in &self.accounts {
+                self.write_assertion(&mut journal, account, as_of);
+            }
+        }
+        journal
+    }
+
+    fn entries(&self, transactions: &[Transaction]) -> Vec<Entry> {
+        let mut transactions: Vec<&Transaction> = transactions
+            .iter()
+            .filter(|transaction| {
+                self.include_held || transaction.attributes.status == TransactionStatus::Settled
+            })
+            .collect();
+        transactions.sort();
+
+        // Pairs each transfer out of an account, and each round-up, with the transaction that
+        // received it, to record the id of the receiving side.
+        let mut received: Vec<Option<&Transaction>> = transactions
+            .iter()
+            .map(|transaction| match is_received_transfer(transaction) {
+                true => Some(*transaction),
+                false => None,
+            })
+            .collect();
+        let mut take_received = |from: &String, to: Option<&String>, amount: i128| {
+            let position = received.iter().position(|candidate| {
+                candidate.is_some_and(|candidate| {
+                    candidate.transfer_account_id() == Some(from)
+                        && to.is_none_or(|to| candidate.account_id() == to)
+                        && candidate.attributes.amount.value_in_base_units == amount
+                })
+            })?;
+            received[position].take()
+        };
+        let mut pairs = Vec::new();
+        for transaction in &transactions {
+            let amount = transaction.attributes.amount.value_in_base_units;
+            let transfer = match transaction.transfer_account_id() {
+                Some(to) if amount < 0 => {
+                    take_received(transaction.account_id(), Some(to), -amount)
+                }
+                _ => None,
+            };
+            let round_up = match &transaction.attributes.round_up {
+                None => None,
+                Some(round_up) => take_received(
+                    transaction.account_id(),
+                    self.round_ups.as_ref(),
+                    -round_up.amount.value_in_base_units,
+                ),
+            };
+            pairs.push((transfer, round_up));
+        }
+
+        let mut entries = Vec::new();
+        for (transaction, (transfer, received_round_up)) in transactions.into_iter().zip(pairs) {
+            // Written with the transfer or round-up it received, paired or not.
+            if is_received_transfer(transaction) {
+                continue;
+            }
+            let ids = |counterpart: Option<&Transaction>| {
+                let mut ids = vec![("up_id", transaction.id.to_string())];
+                if let Some(counterpart) = counterpart {
+                    ids.push(("up_counterpart_id", counterpart.id.to_string()));
+                }
+                ids
+            };
+            if ids(transfer)
+                .iter()
+                .chain(ids(received_round_up).iter())
+                .any(|(_, id)| self.skip.contains(id))
+            {
+                continue;
+            }
+            let attributes = &transaction.attributes;
+            let amount = attributes.amount.value_in_base_units;
+            let account = self.account_name(transaction.account_id());
+            let other = match (transfer, transaction.transfer_account_id()) {
+                (Some(counterpart), _) => self.account_name(counterpart.account_id()),
+                (None, Some(account_id)) => self.account_name(account_id),
+                (None, None) => self.category_account(transaction),
+            };
+            let entry = |ids, postings| Entry {
+                date: attributes
+                    .created_at
+                    .with_timezone(&self.timezone)
+                    .date_naive(),
+                pending: attributes.status == TransactionStatus::Held,
+                description: attributes.description.to_string(),
+                ids,
+                postings,
+                currency: attributes.amount.currency_code.to_string(),
+            };
+            entries.push(entry(
+                ids(transfer),
+                vec![(other, -amount), (account.to_string(), amount)],
+            ));
+            if let Some(round_up) = &attributes.round_up {
+                let amount = round_up.amount.value_in_base_units;
+                let saver = match (received_round_up, &self.round_ups) {
+                    (Some(counterpart), _) => self.account_name(counterpart.account_id()),
+                    (None, Some(account_id)) => self.account_name(account_id),
+                    (None, None) => ROUND_UPS.to_string(),
+                };
+                entries.push(entry(
+                    ids(received_round_up),
+                    vec![(saver, -amount), (account, amount)],
+                ));
+            }
+        }
+        entries
+    }
+
+    fn account_name(&self, account_id: &str) -> String {
+        if let Some(name) = self.account_names.get(account_id) {
+            return name.to_string();
+        }
+        let account = self
+            .accounts
+            .iter()
+            .find(|account| account.id == account_id);
+        let name = account
+            .map(|account| component(&account.attributes.display_name))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| component(account_id));
+        format!("Assets:Up:{}", name)
+    }
+
+    fn category_account(&self, transaction: &Transaction) -> String {
+        let category_id = match transaction.category_id() {
+            Some(category_id) => category_id,
+            None if transaction.attributes.amount.value_in_base_units < 0 => {
+                return self.uncategorized_expenses.to_string()
+            }
+            None => return self.uncategorized_income.to_string(),
+        };
+        if let Some(name) = self.category_accounts.get(category_id) {
+            return name.to_string();
+        }
+        let mut names = vec!["Expenses".to_string()];
+        let category = self
+            .categories
+            .and_then(|tree| tree.get(category_id).map(|category| (tree, category)));
+        match category {
+            None => names.push(component(category_id)),
+            Some((tree, category)) => {
+                for ancestor in tree.ancestors(category_id) {
+                    names.push(component(&ancestor.attributes.name));
+                }
+                names.push(component(&category.attributes.name));
+            }
+        }
+        names.join(":")
+    }
+
+    /// Opens the accounts of the entries and balance assertions that have not been opened yet, on
+    /// the day of the first entry.
+    fn write_opens(&self, journal: &mut String, entries: &[Entry], as_of: NaiveDate) {
+        let mut accounts: BTreeSet<String> = entries
+            .iter()
+            .flat_map(|entry| {
+                entry
+                    .postings
+                    .iter()
+                    .map(|(account, _)| account.to_string())
+            })
+            .collect();
+        if self.balance_assertions {
+            accounts.extend(
+                self.accounts
+                    .iter()
+                    .map(|account| self.account_name(&account.id)),
+            );
+        }
+        accounts.retain(|account| !self.opened.contains(account));
+        if accounts.is_empty() {
+            return;
+        }
+        let date = entries
+            .iter()
+            .fold(as_of, |first, entry| first.min(entry.date));
+        for account in accounts {
+            journal.push_str(&format!("{} open {}\n", date.format("%Y-%m-%d"), account));
+        }
+        journal.push('\n');
+    }
+
+    fn write_entry(&self, journal: &mut String, entry: &Entry) {
+        let flag = if entry.pending { "!" } else { "*" };
+        let description = entry.description.replace(['\r', '\n'], " ");
+        let indent = match self.dialect {
+            Dialect::Beancount => "  ",
+            Dialect::Ledger | Dialect::Hledger => "    ",
+        };
+        match self.dialect {
+            Dialect::Beancount => {
+                journal.push_str(&format!(
+                    "{} {} {}\n",
+                    entry.date.format("%Y-%m-%d"),
+                    flag,
+                    string(&description)
+                ));
+                for (key, id) in &entry.ids {
+                    journal.push_str(&format!("{}{}: {}\n", indent, key, string(id)));
+                }
+            }
+            Dialect::Ledger | Dialect::Hledger => {
+                let date_format = match self.dialect {
+                    Dialect::Ledger => "%Y/%m/%d",
+                    _ => "%Y-%m-%d",
+                };
+                journal.push_str(&format!(
+                    "{} {} {}\n",
+                    entry.date.format(date_format),
+                    flag,
+                    description
+                ));
+                for (key, id) in &entry.ids {
+                    journal.push_str(&format!("{}; {}: {}\n", indent, key, id));
+                }
+            }
+        }
+        // Lines up the accounts and the decimal points of the amounts.
+        let width = entry
+            .postings
+            .iter()
+            .map(|(account, _)| account.chars().count())
+            .max()
+            .unwrap_or_default();
+        let amounts: Vec<String> = entry
+            .postings
+            .iter()
+            .map(|(_, amount)| decimal(*amount))
+            .collect();
+        let amount_width = amounts.iter().map(String::len).max().unwrap_or_default();
+        for ((account, _), amount) in entry.postings.iter().zip(amounts) {
+            journal.push_str(&format!(
+                "{}{:<width$}  {:>amount_width$} {}\n",
+                indent,
+                account,
+                amount,
+                entry.currency,
+                width = width,
+                amount_width = amount_width
+            ));
+        }
+        journal.push('\n');
+    }
+
+    fn write_assertion(&self, journal: &mut String, account: &Account, as_of: NaiveDate) {
+        let name = self.account_name(&account.id);
+        let balance = &account.attributes.balance;
+        let amount = decimal(balance.value_in_base_units);
+        match self.dialect {
+            // Beancount checks balances at the start of the day.
+            Dialect::Beancount => journal.push_str(&format!(
+                "{} balance {}  {} {}\n\n",
+                (as_of + Days::new(1)).format("%Y-%m-%d"),
+                name,
+                amount,
+                balance.currency_code
+            )),
+            Dialect::Ledger | Dialect::Hledger => {
+                let date_format = match self.dialect {
+                    Dialect::Ledger => "%Y/%m/%d",
+                    _ => "%Y-%m-%d",
+                };
+                journal.push_str(&format!(
+                    "{} * Balance of {}\n    {}  0.00 {} = {} {}\n\n",
+                    as_of.format(date_format),
+                    account.attributes.display_name.replace(['\r', '\n'], " "),
+                    name,
+                    balance.currency_code,
+                    amount,
+                    balance.currency_code
+                ))
+            }
+        }
+    }
+}
+
+/// The ids of the transactions in a journal written by `LedgerWriter`, from their metadata.
+pub fn journal_ids(journal: &str) -> HashSet<String> {
+    journal
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_start().trim_start_matches(';').trim_start();
+            let (key, value) = line.split_once(':')?;
+            match key {
+                "up_id" | "up_counterpart_id" => Some(value.trim().trim_matches('"').to_string()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// The accounts opened in a beancount journal written by `LedgerWriter`.
+pub fn journal_accounts(journal: &str) -> HashSet<String> {
+    journal
+        .lines()
+        .filter_map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [_, "open", account, ..] => Some(account.to_string()),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// Turns a name into an account name component, e.g. `Good Life` into `Good-Life`.
+fn component(name: &str) -> String {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first.into_iter().chain(chars).collect()
+        })
+        .collect();
+    words.join("-")
+}
+
+/// An amount in base units as a decimal with two places, e.g. `-12.34`.
+fn decimal(base_units: i128) -> String {
+    let sign = if base_units < 0 { "-" } else { "" };
+    let base_units = base_units.unsigned_abs();
+    format!("{}{}.{:02}", sign, base_units / 100, base_units % 100)
+}
+
+/// A beancount string literal.
+fn string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Whether a transaction is the receiving side of a transfer or round-up from another account.
+fn is_received_transfer(transaction: &Transaction) -> bool {
+    transaction.attributes.amount.value_in_base_units > 0
+        && transaction.transfer_account_id().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::category_tree::CategoryTree;
+    use crate::export::ledger::*;
+    use crate::models::{AccountType, RoundUp};
+    use crate::testing;
+    use chrono::DateTime;
+
+    #[test]
+    fn writes_balanced_entries_once_per_transfer() {
+        let at = |time: &str| {
+            DateTime::parse_from_rfc3339(&format!("2022-10-02T{}+11:00", time)).unwrap()
+        };
+        let accounts = vec![
+            testing::account("spending", "Spending", AccountType::Transactional, 12345),
+            testing::account("saver", "🏠 Home Deposit", AccountType::Saver, 100066),
+        ];
+        let mut pizza =
+            testing::transaction("t1", "spending", "Pizza \"Hut\"", -2334, at("12:00:00"));
+        pizza.attributes.round_up = Some(RoundUp {
+            amount: testing::money(-66),
+            boost_portion: None,
+        });
+        pizza.relationships.category.data = Some(testing::identifier("categories", "takeaway"));
+        let mut round_up = testing::transaction("t2", "saver", "Round Up", 66, at("12:00:01"));
+        round_up.relationships.transfer_account.data =
+            Some(testing::identifier("accounts", "spending"));
+        let mut transfer_out = testing::transaction(
+            "t3",
+            "spending",
+            "Transfer to Home Deposit",
+            -100000,
+            at("13:00:00"),
+        );
+        transfer_out.relationships.transfer_account.data =
+            Some(testing::identifier("accounts", "saver"));
+        let mut transfer_in = testing::transaction(
+            "t4",
+            "saver",
+            "Transfer from Spending",
+            100000,
+            at("13:00:00"),
+        );
+        transfer_in.relationships.transfer_account.data =
+            Some(testing::identifier("accounts", "spending"));
+        let mut held = testing::transaction("t5", "spending", "Cafe", -450, at("14:00:00"));
+        held.attributes.status = TransactionStatus::Held;
+        let transactions = vec![transfer_in, held, round_up, transfer_out, pizza];
+        let tree = CategoryTree::new(vec![
+            testing::category("good-life", "Good Life", None),
+            testing::category("takeaway", "TakeAway", Some("good-life")),
+        ]);
+
+        let writer = LedgerWriter::new(Dialect::Beancount)
+            .accounts(&accounts)
+            .categories(&tree)
+            .as_of(NaiveDate::from_ymd_opt(2022, 10, 2).unwrap());
+        let journal = writer.to_journal(&transactions);
+        assert_eq!(
+            journal,
+            "2022-10-02 open Assets:Up:Home-Deposit\n\
+             2022-10-02 open Assets:Up:Spending\n\
+             2022-10-02 open Expenses:Good-Life:TakeAway\n\
+             2022-10-02 open Expenses:Uncategorized\n\n\
+             2022-10-02 * \"Pizza \\\"Hut\\\"\"\n  up_id: \"t1\"\n  \
+             Expenses:Good-Life:TakeAway   23.34 AUD\n  \
+             Assets:Up:Spending           -23.34 AUD\n\n\
+             2022-10-02 * \"Pizza \\\"Hut\\\"\"\n  up_id: \"t1\"\n  up_counterpart_id: \"t2\"\n  \
+             Assets:Up:Home-Deposit   0.66 AUD\n  \
+             Assets:Up:Spending      -0.66 AUD\n\n\
+             2022-10-02 * \"Transfer to Home Deposit\"\n  up_id: \"t3\"\n  \
+             up_counterpart_id: \"t4\"\n  \
+             Assets:Up:Home-Deposit   1000.00 AUD\n  \
+             Assets:Up:Spending      -1000.00 AUD\n\n\
+             2022-10-02 ! \"Cafe\"\n  up_id: \"t5\"\n  \
+             Expenses:Uncategorized   4.50 AUD\n  \
+             Assets:Up:Spending      -4.50 AUD\n\n\
+             2022-10-03 balance Assets:Up:Spending  123.45 AUD\n\n\
+             2022-10-03 balance Assets:Up:Home-Deposit  1000.66 AUD\n\n"
+        );
+
+        let written = journal_ids(&journal);
+        assert_eq!(written.len(), 5);
+        let appended = writer
+            .skip(&written)
+            .opened(&journal_accounts(&journal))
+            .balance_assertions(false)
+            .to_journal(&transactions);
+        assert_eq!(appended, "");
+        let appended = LedgerWriter::new(Dialect::Ledger)
+            .accounts(&accounts)
+            .balance_assertions(false)
+            .skip(&written)
+            .to_journal(&transactions);
+        assert_eq!(appended, "");
+
+        let writer = LedgerWriter::new(Dialect::Ledger)
+            .accounts(&accounts)
+            .round_ups("saver")
+            .balance_assertions(false);
+        let saver = writer.to_journal(&[transactions[0].clone(), transactions[2].clone()]);
+        assert_eq!(saver, "");
+        let spending = writer
+            .skip(&journal_ids(&saver))
+            .to_journal(&[transactions[3].clone(), transactions[4].clone()]);
+        assert_eq!(spending.matches("; up_id:").count(), 3);
+        assert_eq!(spending.matches("Assets:Up:Home-Deposit").count(), 2);
+
+        let ledger = LedgerWriter::new(Dialect::Ledger)
+            .account("spending", "Assets:Up")
+            .balance_assertions(false)
+            .to_journal(&transactions[1..2]);
+        assert_eq!(
+            ledger,
+            "2022/10/02 ! Cafe\n    ; up_id: t5\n    \
+             Expenses:Uncategorized   4.50 AUD\n    \
+             Assets:Up               -4.50 AUD\n\n"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use crate::models::{Account, AccountType, Transaction, TransactionStatus};
+use chrono::{DateTime, FixedOffset, Utc};
+use std::fmt::Write as _;
+use std::io::Write;
+
+/// The BSB of Up accounts, used as the bank id.
+const UP_BSB: &str = "633123";
+
+/// The longest `NAME` OFX allows. Longer descriptions are cut and written in full to `MEMO`.
+const MAX_NAME_LENGTH: usize = 32;
+
+/// Writes the transactions of an account as an OFX 2.2 bank statement, with the balance of the
+/// account as the ledger balance.
+///
+/// Transaction ids are used as `FITID`s, so importing overlapping statements does not duplicate
+/// transactions. Held transactions are left out unless `include_held` is set, as their amount can
+/// still change when they settle.
+pub struct OfxWriter<'a> {
+    account: &'a Account,
+    include_held: bool,
+    as_of: DateTime<FixedOffset>,
+}
+
+impl<'a> OfxWriter<'a> {
+    pub fn new(account: &'a Account) -> OfxWriter<'a> {
+        OfxWriter {
+            account,
+            include_held: false,
+            as_of: Utc::now().fixed_offset(),
+        }
+    }
+
+    pub fn include_held(mut self, include_held: bool) -> OfxWriter<'a> {
+        self.include_held = include_held;
+        self
+    }
+
+    /// When the balance of the account was fetched. Defaults to now.
+    pub fn as_of(mut self, as_of: DateTime<FixedOffset>) -> OfxWriter<'a> {
+        self.as_of = as_of;
+        self
+    }
+
+    pub fn write<W: Write>(
+        &self,
+        mut writer: W,
+        transactions: &[Transaction],
+    ) -> Result<(), String> {
+        writer
+            .write_all(self.to_ofx(transactions).as_bytes())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn to_ofx(&self, transactions: &[Transaction]) -> String {
+        let mut transactions: Vec<&Transaction> = transactions
+            .iter()
+            .filter(|transaction| transaction.account_id() == &self.account.id)
+            .filter(|transaction| {
+                self.include_held || transaction.attributes.status == TransactionStatus::Settled
+            })
+            .collect();
+        transactions.sort();
+        let start = transactions
+            .first()
+            .map_or(self.as_of, |transaction| posted_at(transaction));
+        let balance = &self.account.attributes.balance;
+        let account_type = match self.account.attributes.account_type {
+            AccountType::Transactional => "CHECKING",
+            AccountType::Saver => "SAVINGS",
+        };
+
+        let mut ofx = String::new();
+        ofx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+        ofx.push_str(
+            "<?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" OLDFILEUID=\"NONE\" \
+             NEWFILEUID=\"NONE\"?>\n",
+        );
+        ofx.push_str("<OFX>\n<SIGNONMSGSRSV1>\n<SONRS>\n");
+        ofx.push_str("<STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>\n");
+        let _ = writeln!(ofx, "<DTSERVER>{}</DTSERVER>", date_time(&self.as_of));
+        ofx.push_str("<LANGUAGE>ENG</LANGUAGE>\n</SONRS>\n</SIGNONMSGSRSV1>\n");
+        ofx.push_str("<BANKMSGSRSV1>\n<STMTTRNRS>\n<TRNUID>0</TRNUID>\n");
+        ofx.push_str("<STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>\n");
+        let _ = writeln!(ofx, "<STMTRS>\n<CURDEF>{}</CURDEF>", balance.currency_code);
+        let _ = writeln!(
+            ofx,
+            "<BANKACCTFROM><BANKID>{}</BANKID><ACCTID>{}</ACCTID><ACCTTYPE>{}</ACCTTYPE>\
+             </BANKACCTFROM>",
+            UP_BSB,
+            escape(&self.account.id),
+            account_type
+        );
+        let _ = writeln!(
+            ofx,
+            "<BANKTRANLIST>\n<DTSTART>{}</DTSTART>\n<DTEND>{}</DTEND>",
+            date_time(&start),
+            date_time(&self.as_of)
+        );
+        for transaction in transactions {
+            write_transaction(&mut ofx, transaction);
+        }
+        ofx.push_str("</BANKTRANLIST>\n");
+        let _ = writeln!(
+            ofx,
+            "<LEDGERBAL><BALAMT>{}</BALAMT><DTASOF>{}</DTASOF></LEDGERBAL>",
+            balance.value,
+            date_time(&self.as_of)
+        );
+        ofx.push_str("</STMTRS>\n</STMTTRNRS>\n</BANKMSGSRSV1>\n</OFX>\n");
+        ofx
+    }
+}
+
+fn write_transaction(ofx: &mut String, transaction: &Transaction) {
+    let attributes = &transaction.attributes;
+    let transaction_type = match attributes.amount.value_in_base_units < 0 {
+        true => "DEBIT",
+        false => "CREDIT",
+    };
+    let name: String = attributes
+        .description
+        .chars()
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    let memo = match (&attributes.message, &attributes.raw_text) {
+        (Some(message), _) => Some(message.to_string()),
+        (None, _) if name != attributes.description => Some(attributes.description.to_string()),
+        (None, raw_text) => raw_text.clone(),
+    };
+    ofx.push_str("<STMTTRN>\n");
+    let _ = writeln!(ofx, "<TRNTYPE>{}</TRNTYPE>", transaction_type);
+    let _ = writeln!(
+        ofx,
+        "<DTPOSTED>{}</DTPOSTED>",
+        date_time(&posted_at(transaction))
+    );
+    let _ = writeln!(
+        ofx,
+        "<DTUSER>{}</DTUSER>",
+        date_time(&attributes.created_at)
+    );
+    let _ = writeln!(ofx, "<TRNAMT>{}</TRNAMT>", attributes.amount.value);
+    let _ = writeln!(ofx, "<FITID>{}</FITID>", escape(&transaction.id));
+    let _ = writeln!(ofx, "<NAME>{}</NAME>", escape(&name));
+    if let Some(memo) = memo {
+        let _ = writeln!(ofx, "<MEMO>{}</MEMO>", escape(&memo));
+    }
+    ofx.push_str("</STMTTRN>\n");
+}
+
+fn posted_at(transaction: &Transaction) -> DateTime<FixedOffset> {
+    transaction
+        .attributes
+        .settled_at
+        .unwrap_or(transaction.attributes.created_at)
+}
+
+/// An OFX date-time in UTC, e.g. `20221001093000.000[0:GMT]`.
+fn date_time(date_time: &DateTime<FixedOffset>) -> String {
+    format!(
+        "{}[0:GMT]",
+        date_time.with_timezone(&Utc).format("%Y%m%d%H%M%S%.3f")
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::ofx::*;
+    use crate::testing::{self, at};
+
+    #[test]
+    fn writes_a_statement_with_fitids_and_ledger_balance() {
+        let account = testing::account("spending", "Spending", AccountType::Transactional, 12345);
+        let mut held = testing::transaction(
+            "t3",
+            "spending",
+            "Pending",
+            -100,
+            at("2022-10-03T09:00:00+11:00"),
+        );
+        held.attributes.status = TransactionStatus::Held;
+        let transactions = vec![
+            testing::transaction(
+                "t2",
+                "spending",
+                "Fish & Chips <Beach>",
+                -2345,
+                at("2022-10-02T12:00:00+11:00"),
+            ),
+            testing::transaction(
+                "t1",
+                "spending",
+                "Salary",
+                500000,
+                at("2022-10-01T09:00:00+10:00"),
+            ),
+            testing::transaction(
+                "s1",
+                "saver",
+                "Interest",
+                12,
+                at("2022-10-01T09:00:00+10:00"),
+            ),
+            held,
+        ];
+        let ofx = OfxWriter::new(&account)
+            .as_of(at("2022-10-04T00:00:00+00:00"))
+            .to_ofx(&transactions);
+
+        assert!(ofx.starts_with("<?xml version=\"1.0\""));
+        assert!(ofx.contains("<ACCTID>spending</ACCTID><ACCTTYPE>CHECKING</ACCTTYPE>"));
+        assert!(ofx.contains("<DTSTART>20220930230000.000[0:GMT]</DTSTART>"));
+        assert!(ofx.contains(
+            "<LEDGERBAL><BALAMT>123.45</BALAMT><DTASOF>20221004000000.000[0:GMT]</DTASOF>"
+        ));
+        assert!(ofx
+            .contains("<TRNTYPE>CREDIT</TRNTYPE>\n<DTPOSTED>20220930230000.000[0:GMT]</DTPOSTED>"));
+        assert!(ofx.contains("<TRNAMT>-23.45</TRNAMT>\n<FITID>t2</FITID>"));
+        assert!(ofx.contains("<NAME>Fish &amp; Chips &lt;Beach&gt;</NAME>"));
+        assert!(ofx.find("<FITID>t1</FITID>") < ofx.find("<FITID>t2</FITID>"));
+        assert!(!ofx.contains("<FITID>t3</FITID>"));
+        assert!(!ofx.contains("<FITID>s1</FITID>"));
+
+        let ofx = OfxWriter::new(&account)
+            .include_held(true)
+            .to_ofx(&transactions);
+        assert!(ofx.contains("<FITID>t3</FITID>"));
+    }
+}
@@ -0,0 +1,158 @@
+use crate::category_tree::CategoryTree;
+use crate::dates::{Tz, DEFAULT_TIMEZONE};
+use crate::export::csv::is_valid_date_format;
+use crate::models::{Account, Transaction, TransactionStatus};
+use std::io::Write;
+
+/// Writes the transactions of an account as QIF.
+///
+/// QIF has no field for a unique id, so the transaction id is written as the reference number
+/// (`N`), which tools such as GnuCash use to match transactions on repeated imports. Categories
+/// are written by name, as `Parent:Child`, when a `CategoryTree` is given. Held transactions are
+/// left out unless `include_held` is set.
+pub struct QifWriter<'a> {
+    account: &'a Account,
+    categories: Option<&'a CategoryTree>,
+    include_held: bool,
+    timezone: Tz,
+    date_format: String,
+}
+
+impl<'a> QifWriter<'a> {
+    /// A writer that writes dates as `01/10/2022` in Australia/Melbourne.
+    pub fn new(account: &'a Account) -> QifWriter<'a> {
+        QifWriter {
+            account,
+            categories: None,
+            include_held: false,
+            timezone: DEFAULT_TIMEZONE,
+            date_format: "%d/%m/%Y".to_string(),
+        }
+    }
+
+    pub fn categories(mut self, categories: &'a CategoryTree) -> QifWriter<'a> {
+        self.categories = Some(categories);
+        self
+    }
+
+    pub fn include_held(mut self, include_held: bool) -> QifWriter<'a> {
+        self.include_held = include_held;
+        self
+    }
+
+    pub fn timezone(mut self, timezone: Tz) -> QifWriter<'a> {
+        self.timezone = timezone;
+        self
+    }
+
+    /// A `chrono` format string for dates, e.g. `%m/%d/%Y` for software that expects US dates.
+    pub fn date_format(mut self, date_format: &str) -> QifWriter<'a> {
+        if is_valid_date_format(date_format) {
+            self.date_format = date_format.to_string();
+        } else {
+            eprintln!("{} is not a valid date format.", date_format);
+        }
+        self
+    }
+
+    pub fn write<W: Write>(
+        &self,
+        mut writer: W,
+        transactions: &[Transaction],
+    ) -> Result<(), String> {
+        writer
+            .write_all(self.to_qif(transactions).as_bytes())
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn to_qif(&self, transactions: &[Transaction]) -> String {
+        let mut transactions: Vec<&Transaction> = transactions
+            .iter()
+            .filter(|transaction| transaction.account_id() == &self.account.id)
+            .filter(|transaction| {
+                self.include_held || transaction.attributes.status == TransactionStatus::Settled
+            })
+            .collect();
+        transactions.sort();
+
+        let mut qif = format!(
+            "!Account\nN{}\nTBank\n^\n!Type:Bank\n",
+            line(&self.account.attributes.display_name)
+        );
+        for transaction in transactions {
+            let attributes = &transaction.attributes;
+            let date = attributes.created_at.with_timezone(&self.timezone);
+            qif.push_str(&format!("D{}\n", date.format(&self.date_format)));
+            qif.push_str(&format!("T{}\n", attributes.amount.value));
+            qif.push_str(&format!("N{}\n", line(&transaction.id)));
+            qif.push_str(&format!("P{}\n", line(&attributes.description)));
+            if let Some(memo) = attributes.message.as_ref().or(attributes.raw_text.as_ref()) {
+                qif.push_str(&format!("M{}\n", line(memo)));
+            }
+            if let Some(category) = self.category(transaction) {
+                qif.push_str(&format!("L{}\n", line(&category)));
+            }
+            qif.push_str("^\n");
+        }
+        qif
+    }
+
+    fn category(&self, transaction: &Transaction) -> Option<String> {
+        let category_id = transaction.category_id()?;
+        let category = match self.categories.and_then(|tree| tree.get(category_id)) {
+            None => return Some(category_id.to_string()),
+            Some(category) => category,
+        };
+        let mut names: Vec<&str> = self
+            .categories?
+            .ancestors(category_id)
+            .iter()
+            .map(|ancestor| ancestor.attributes.name.as_str())
+            .collect();
+        names.push(&category.attributes.name);
+        Some(names.join(":"))
+    }
+}
+
+/// QIF fields end at the end of the line.
+fn line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::category_tree::CategoryTree;
+    use crate::export::qif::*;
+    use crate::models::AccountType;
+    use crate::testing;
+
+    #[test]
+    fn writes_transactions_with_ids_and_category_paths() {
+        let account = testing::account("spending", "Spending", AccountType::Transactional, 0);
+        let mut pizza = testing::transaction(
+            "t1",
+            "spending",
+            "Pizza Hut",
+            -2345,
+            testing::at("2022-10-01T23:30:00+00:00"),
+        );
+        pizza.attributes.message = Some("Friday\nnight".to_string());
+        pizza.relationships.category.data = Some(testing::identifier("categories", "takeaway"));
+        let tree = CategoryTree::new(vec![
+            testing::category("good-life", "Good Life", None),
+            testing::category("takeaway", "TakeAway", Some("good-life")),
+        ]);
+
+        let qif = QifWriter::new(&account)
+            .categories(&tree)
+            .to_qif(&[pizza.clone()]);
+        assert_eq!(
+            qif,
+            "!Account\nNSpending\nTBank\n^\n!Type:Bank\n\
+             D02/10/2022\nT-23.45\nNt1\nPPizza Hut\nMFriday night\nLGood Life:TakeAway\n^\n"
+        );
+
+        let qif = QifWriter::new(&account).date_format("%Q").to_qif(&[pizza]);
+        assert!(qif.contains("\nD02/10/2022\n"));
+    }
+}
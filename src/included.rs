@@ -0,0 +1,157 @@
+use crate::models::*;
+use std::collections::HashMap;
+
+/// An identity map over the `included` resources of a response, keyed by type and id.
+///
+/// Each resource is stored once, so every relationship pointing at the same resource resolves to
+/// the same object.
+pub struct IncludedResources {
+    resources: HashMap<(String, String), Resource>,
+}
+
+impl IncludedResources {
+    pub fn new(included: Vec<Resource>) -> IncludedResources {
+        let mut resources: HashMap<(String, String), Resource> = HashMap::new();
+        for resource in included {
+            if let (Some(resource_type), Some(id)) = (resource.resource_type(), resource.id()) {
+                resources.insert((resource_type.to_string(), id.to_string()), resource);
+            }
+        }
+        IncludedResources { resources }
+    }
+
+    pub fn len(&self) -> usize {
+        self.resources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.resources.is_empty()
+    }
+
+    pub fn resolve(&self, identifier: &ResourceIdentifier) -> Option<&Resource> {
+        self.resources.get(&(
+            identifier.resource_type.to_string(),
+            identifier.id.to_string(),
+        ))
+    }
+
+    pub fn account(&self, id: &str) -> Option<&Account> {
+        match self
+            .resources
+            .get(&("accounts".to_string(), id.to_string()))
+        {
+            Some(Resource::Account(account)) => Some(account),
+            _ => None,
+        }
+    }
+
+    pub fn category(&self, id: &str) -> Option<&Category> {
+        match self
+            .resources
+            .get(&("categories".to_string(), id.to_string()))
+        {
+            Some(Resource::Category(category)) => Some(category),
+            _ => None,
+        }
+    }
+
+    pub fn tag(&self, id: &str) -> Option<&Tag> {
+        match self.resources.get(&("tags".to_string(), id.to_string())) {
+            Some(Resource::Tag(tag)) => Some(tag),
+            _ => None,
+        }
+    }
+
+    pub fn transaction(&self, id: &str) -> Option<&Transaction> {
+        match self
+            .resources
+            .get(&("transactions".to_string(), id.to_string()))
+        {
+            Some(Resource::Transaction(transaction)) => Some(transaction),
+            _ => None,
+        }
+    }
+
+    pub fn account_of(&self, transaction: &Transaction) -> Option<&Account> {
+        self.account(transaction.account_id())
+    }
+
+    pub fn transfer_account_of(&self, transaction: &Transaction) -> Option<&Account> {
+        transaction
+            .transfer_account_id()
+            .and_then(|id| self.account(id))
+    }
+
+    pub fn category_of(&self, transaction: &Transaction) -> Option<&Category> {
+        transaction.category_id().and_then(|id| self.category(id))
+    }
+
+    pub fn parent_category_of(&self, transaction: &Transaction) -> Option<&Category> {
+        transaction
+            .parent_category_id()
+            .and_then(|id| self.category(id))
+    }
+
+    /// The included tags of a transaction. Tags that were not included are skipped.
+    pub fn tags_of(&self, transaction: &Transaction) -> Vec<&Tag> {
+        transaction
+            .tag_ids()
+            .into_iter()
+            .filter_map(|id| self.tag(id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::*;
+    use crate::request_sender::ApiResponse;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn resolves_included_resources() {
+        let json: String = fs::read_to_string("tests/example_json/list_transactions.json").unwrap();
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["data"][0]["relationships"]["category"]["data"] =
+            json!({ "type": "categories", "id": "takeaway" });
+        value["meta"] = json!({ "count": 1 });
+        value["included"] = json!([
+            {
+                "type": "categories",
+                "id": "takeaway",
+                "attributes": { "name": "Takeaway" },
+                "relationships": {
+                    "parent": { "data": { "type": "categories", "id": "good-life" } },
+                    "children": { "data": [] }
+                }
+            },
+            {
+                "type": "tags",
+                "id": "Pizza Night",
+                "relationships": { "transactions": { "links": {} } }
+            },
+            { "type": "webhooks", "id": "1" }
+        ]);
+        let des: ApiResponse<Vec<Transaction>> = serde_json::from_value(value).unwrap();
+        assert_eq!(des.meta, Some(json!({ "count": 1 })));
+
+        let included = des.included_resources();
+        let transaction = &des.data[0];
+        assert_eq!(included.len(), 3);
+        assert_eq!(
+            included.category_of(transaction).unwrap().attributes.name,
+            "Takeaway"
+        );
+        assert!(included.parent_category_of(transaction).is_none());
+        assert!(included.account_of(transaction).is_none());
+        assert_eq!(included.tags_of(transaction)[0].id, "Pizza Night");
+        assert!(matches!(
+            included.resolve(&ResourceIdentifier {
+                resource_type: "webhooks".to_string(),
+                id: "1".to_string()
+            }),
+            Some(Resource::Other(_))
+        ));
+    }
+}
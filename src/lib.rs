@@ -1,7 +1,7 @@
 //! # Up Bank API wrapper
 //! This crate is an API wrapper for the [Up Bank API](https://developer.up.com.au/).
 //! ## Example Usage
-//! ```
+//! ```no_run
 //! use uprs::api_endpoints::ListAccounts;
 //! use uprs::models::Account;
 //! use uprs::request_sender::ApiRequest;
@@ -17,6 +17,20 @@
 //!     }
 //! }
 //! ```
+//! ## Features
+//! - `rustls-tls` (default): sends requests with `reqwest` using rustls.
+//! - `native-tls`: sends requests with `reqwest` using the platform's TLS library.
+//! - `blocking`: adds `blocking::ApiRequest` for sending requests without an async runtime.
+//! - `tracing`: emits `tracing` spans for each request and each page of a paginated request.
+//! - `sqlite`: adds `store::SqliteStore`, which keeps synced data in an SQLite database.
+//! - `mock`: adds `testing::MockUpApi`, an in-process fake of the API for tests.
+//! - `cli`: builds the `uprs` command-line tool, e.g. `uprs transactions --last-days 7`. It reads
+//!   the token from `--token` or the `UP_API_TOKEN` environment variable.
+//!
+//! With the default features disabled only the models and request builders are compiled, and
+//! neither `reqwest` nor `tokio` is pulled in.
+
+#![allow(clippy::needless_return)]
 
 /// Contains the different models as structs used in the API (e.g. Account, Transaction).
 pub mod models;
@@ -24,3 +38,52 @@ pub mod models;
 pub mod api_endpoints;
 /// Contains structs and traits that handle sending requests to the API.
 pub mod request_sender;
+/// Contains a synchronous `ApiRequest` trait, implemented by the endpoints in `api_endpoints`,
+/// for sending requests without an async runtime. Requires the `blocking` feature.
+#[cfg(feature = "blocking")]
+pub mod blocking;
+/// Contains the `HttpTransport` trait that requests are sent through, and its `reqwest`
+/// implementation.
+pub mod transport;
+/// Contains the client that holds the credentials used to send requests to the API.
+pub mod client;
+/// Contains methods on the models for following their relationships to other resources.
+pub mod relationships;
+/// Contains a tree of categories for navigating the category hierarchy.
+pub mod category_tree;
+/// Contains an identity map for resolving relationships against the `included` resources of a
+/// response.
+pub mod included;
+/// Contains helpers for turning calendar dates into date-time bounds in a timezone.
+pub mod dates;
+/// Contains the `Token` type that holds the personal access token.
+pub mod token;
+/// Contains the client-side rate limit that can be set on an `UpClient`.
+pub mod rate_limit;
+/// Contains `Batch` for retrieving many resources by id.
+pub mod batch;
+/// Contains `Mirror`, which keeps a local copy of the accounts and transactions up to date and
+/// reports what changed.
+pub mod sync;
+/// Contains `HoldTracker`, which follows held transactions until they settle or vanish.
+pub mod lifecycle;
+/// Contains the `Store` trait for keeping synced data on disk, with a JSON-lines store and, with
+/// the `sqlite` feature, an SQLite store.
+pub mod store;
+/// Contains writers that export transactions for spreadsheets and accounting software.
+pub mod export;
+/// Contains `StatementReader` and `reconcile`, which check a CSV statement against the
+/// transactions from the API.
+pub mod reconcile;
+/// Contains `Query`, a small language for filtering transactions that passes what it can on to
+/// the API.
+pub mod query;
+/// Contains the `CancelToken` for stopping paginated requests part way.
+pub mod cancel;
+mod trace;
+/// Contains transports that record API traffic into cassette files and replay it offline.
+pub mod cassette;
+/// Contains an in-process fake of the Up API for testing code that uses this crate. Requires the
+/// `mock` feature.
+#[cfg(any(test, feature = "mock"))]
+pub mod testing;
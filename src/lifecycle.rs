@@ -0,0 +1,221 @@
+use crate::models::{Transaction, TransactionStatus};
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Something notable about a hold, reported by `HoldTracker::observe`.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum HoldEvent {
+    /// A hold settled for a different amount, e.g. fuel or a hotel pre-authorisation.
+    /// `difference` is the settled amount minus the held amount in base units, so it is positive
+    /// when less was taken than was held.
+    SettledDifferently {
+        hold: Transaction,
+        settled: Transaction,
+        difference: i128,
+    },
+    /// A hold that is gone without having settled, usually a released pre-authorisation.
+    Vanished(Transaction),
+    /// A hold that has been pending for longer than the threshold. Each hold is reported once.
+    Overdue {
+        hold: Transaction,
+        pending_for: Duration,
+    },
+}
+
+/// Follows held transactions through successive snapshots of the transactions, e.g. the
+/// transactions of a `sync::Mirror` after each sync.
+///
+/// Each snapshot has to contain every transaction that is still held, or the missing holds are
+/// reported as vanished. A hold that settles under a new id is also reported as vanished. The
+/// tracker can be serialized to carry it between runs.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HoldTracker {
+    threshold_seconds: i64,
+    holds: HashMap<String, Transaction>,
+    overdue: HashSet<String>,
+}
+
+impl HoldTracker {
+    /// Reports holds that have been pending for longer than `threshold`.
+    pub fn new(threshold: Duration) -> HoldTracker {
+        HoldTracker {
+            threshold_seconds: threshold.num_seconds(),
+            holds: HashMap::new(),
+            overdue: HashSet::new(),
+        }
+    }
+
+    /// The holds that are being followed.
+    pub fn get_holds(&self) -> Vec<&Transaction> {
+        let mut holds: Vec<&Transaction> = self.holds.values().collect();
+        holds.sort();
+        holds
+    }
+
+    /// Compares `snapshot` with the previous snapshots.
+    pub fn observe<'a, I: IntoIterator<Item = &'a Transaction>>(
+        &mut self,
+        snapshot: I,
+    ) -> Vec<HoldEvent> {
+        self.observe_at(snapshot, Utc::now().fixed_offset())
+    }
+
+    fn observe_at<'a, I: IntoIterator<Item = &'a Transaction>>(
+        &mut self,
+        snapshot: I,
+        now: DateTime<FixedOffset>,
+    ) -> Vec<HoldEvent> {
+        let mut events = Vec::new();
+        let mut seen = HashSet::new();
+        let mut snapshot: Vec<&Transaction> = snapshot.into_iter().collect();
+        snapshot.sort();
+        for transaction in snapshot {
+            seen.insert(transaction.id.to_string());
+            match transaction.attributes.status {
+                TransactionStatus::Held => {
+                    self.holds
+                        .insert(transaction.id.to_string(), transaction.clone());
+                }
+                TransactionStatus::Settled => {
+                    if let Some(hold) = self.holds.remove(&transaction.id) {
+                        self.overdue.remove(&transaction.id);
+                        events.extend(settled_differently(hold, transaction));
+                    }
+                }
+            }
+        }
+
+        let mut vanished: Vec<Transaction> = self
+            .holds
+            .values()
+            .filter(|hold| !seen.contains(&hold.id))
+            .cloned()
+            .collect();
+        vanished.sort();
+        for hold in vanished {
+            self.holds.remove(&hold.id);
+            self.overdue.remove(&hold.id);
+            events.push(HoldEvent::Vanished(hold));
+        }
+
+        let threshold = Duration::seconds(self.threshold_seconds);
+        for hold in self.get_holds() {
+            let pending_for = now - hold.attributes.created_at;
+            if pending_for > threshold && !self.overdue.contains(&hold.id) {
+                events.push(HoldEvent::Overdue {
+                    hold: hold.clone(),
+                    pending_for,
+                });
+            }
+        }
+        for event in &events {
+            if let HoldEvent::Overdue { hold, .. } = event {
+                self.overdue.insert(hold.id.to_string());
+            }
+        }
+        events
+    }
+}
+
+/// The held amount is taken from the `holdInfo` of the settled transaction, which Up keeps, or
+/// else from the hold as it was last seen.
+fn settled_differently(hold: Transaction, settled: &Transaction) -> Option<HoldEvent> {
+    let held = match &settled.attributes.hold_info {
+        Some(hold_info) => hold_info.amount.value_in_base_units,
+        None => hold.attributes.amount.value_in_base_units,
+    };
+    let difference = settled.attributes.amount.value_in_base_units - held;
+    match difference {
+        0 => None,
+        _ => Some(HoldEvent::SettledDifferently {
+            hold,
+            settled: settled.clone(),
+            difference,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lifecycle::*;
+    use crate::models::HoldInfo;
+    use crate::testing::{self, at, held_transaction};
+
+    fn settled(hold: &Transaction, amount: i128) -> Transaction {
+        let mut transaction = hold.clone();
+        transaction.attributes.status = TransactionStatus::Settled;
+        transaction.attributes.hold_info = Some(HoldInfo {
+            amount: hold.attributes.amount.clone(),
+            foreign_amount: None,
+        });
+        transaction.attributes.amount = testing::money(amount);
+        transaction
+    }
+
+    #[test]
+    fn reports_the_lifecycle_of_holds() {
+        let fuel = held_transaction(
+            "fuel",
+            "spending",
+            "fuel",
+            -10000,
+            at("2022-10-01T09:00:00+10:00"),
+        );
+        let hotel = held_transaction(
+            "hotel",
+            "spending",
+            "hotel",
+            -50000,
+            at("2022-10-01T12:00:00+10:00"),
+        );
+        let coffee = held_transaction(
+            "coffee",
+            "spending",
+            "coffee",
+            -450,
+            at("2022-10-03T08:00:00+11:00"),
+        );
+        let mut tracker = HoldTracker::new(Duration::days(3));
+
+        let first = [fuel.clone(), hotel.clone(), coffee.clone()];
+        let events = tracker.observe_at(&first, at("2022-10-03T09:00:00+11:00"));
+        assert!(events.is_empty());
+        assert_eq!(tracker.get_holds().len(), 3);
+
+        let second = [settled(&fuel, -6543), settled(&coffee, -450)];
+        let events = tracker.observe_at(&second, at("2022-10-04T09:00:00+11:00"));
+        assert_eq!(
+            events,
+            vec![
+                HoldEvent::SettledDifferently {
+                    hold: fuel.clone(),
+                    settled: settled(&fuel, -6543),
+                    difference: 3457,
+                },
+                HoldEvent::Vanished(hotel.clone()),
+            ]
+        );
+
+        let late = held_transaction(
+            "car-hire",
+            "spending",
+            "car-hire",
+            -30000,
+            at("2022-10-04T10:00:00+11:00"),
+        );
+        tracker.observe_at([&late], at("2022-10-05T10:00:00+11:00"));
+        let events = tracker.observe_at([&late], at("2022-10-08T10:00:01+11:00"));
+        assert_eq!(
+            events,
+            vec![HoldEvent::Overdue {
+                hold: late.clone(),
+                pending_for: Duration::days(4) + Duration::seconds(1),
+            }]
+        );
+        assert!(tracker
+            .observe_at([&late], at("2022-10-09T10:00:00+11:00"))
+            .is_empty());
+    }
+}
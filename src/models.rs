@@ -1,10 +1,10 @@
 use chrono::{DateTime, FixedOffset};
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 
-
-
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Account {
     #[serde(rename = "type")]
     pub resource_type: String,
@@ -14,12 +14,26 @@ pub struct Account {
     pub links: HashMap<String, String>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+impl PartialEq for Account {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Account {}
+
+impl Hash for Account {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct AccountRelationships {
     pub transactions: AccountTransactionsRelationships,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct AccountAttributes {
     #[serde(rename = "displayName")]
     pub display_name: String,
@@ -32,7 +46,18 @@ pub struct AccountAttributes {
     pub created_at: DateTime<FixedOffset>,
 }
 
-#[derive(Deserialize, Debug, strum_macros::Display, PartialEq, Eq)]
+/// The value the API uses for one of the enums below, e.g. `SETTLED` for
+/// `TransactionStatus::Settled`. `Display` shows the variant name instead.
+pub(crate) fn api_value<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(value)) => value,
+        _ => String::new(),
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, strum_macros::Display, PartialEq, Eq, Hash,
+)]
 pub enum AccountType {
     #[serde(rename = "SAVER")]
     Saver,
@@ -40,7 +65,9 @@ pub enum AccountType {
     Transactional,
 }
 
-#[derive(Deserialize, Debug, strum_macros::Display, PartialEq, Eq)]
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, strum_macros::Display, PartialEq, Eq, Hash,
+)]
 pub enum OwnershipType {
     #[serde(rename = "INDIVIDUAL")]
     Individual,
@@ -48,7 +75,7 @@ pub enum OwnershipType {
     Joint,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Money {
     #[serde(rename = "currencyCode")]
     pub currency_code: String,
@@ -57,12 +84,12 @@ pub struct Money {
     pub value_in_base_units: i128,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct AccountTransactionsRelationships {
-    pub links: HashMap<String, String>,
+    pub links: RelationshipLinks,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Category {
     #[serde(rename = "type")]
     pub resource_type: String,
@@ -72,55 +99,147 @@ pub struct Category {
     pub links: Option<HashMap<String, String>>,
 }
 
+impl PartialEq for Category {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Category {}
+
+impl Hash for Category {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
 impl Category {
     pub(crate) fn to_param(&self) -> &String {
         &self.id
     }
+
+    pub fn parent_id(&self) -> Option<&String> {
+        self.relationships
+            .parent
+            .data
+            .as_ref()
+            .map(|parent| &parent.id)
+    }
+
+    pub fn child_ids(&self) -> Vec<&String> {
+        match &self.relationships.children.data {
+            None => Vec::new(),
+            Some(children) => children.iter().map(|child| &child.id).collect(),
+        }
+    }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct CategoryAttributes {
     pub name: String,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct CategoryRelationships {
     pub parent: ParentRelationship,
     pub children: ChildRelationship,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ChildRelationship {
-    pub data: Option<Vec<HashMap<String, String>>>,
-    pub links: Option<HashMap<String, String>>,
+    pub data: Option<Vec<ResourceIdentifier>>,
+    pub links: Option<RelationshipLinks>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ParentRelationship {
-    pub data: Option<HashMap<String, String>>,
-    pub links: Option<HashMap<String, String>>,
+    pub data: Option<ResourceIdentifier>,
+    pub links: Option<RelationshipLinks>,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
-struct Payload<T> {
-    data: T,
+/// Identifies a related resource by its type and id,
+/// e.g. `{"type": "categories", "id": "hobbies"}`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ResourceIdentifier {
+    #[serde(rename = "type")]
+    pub resource_type: String,
+    pub id: String,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
-struct CategorizeTransactionData {
-    #[serde(rename = "type")]
-    resource_type: String,
-    id: String,
+/// The links attached to a relationship. `self_` points at the relationship itself and
+/// `related` points at the related resource(s).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RelationshipLinks {
+    #[serde(rename = "self")]
+    pub self_: Option<String>,
+    pub related: Option<String>,
+}
+
+/// A resource of any type, as found in the `included` array of a response.
+///
+/// Resources are told apart by their `type`. Types this crate does not model are kept as raw JSON.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resource {
+    Account(Account),
+    Category(Category),
+    Tag(Tag),
+    Transaction(Transaction),
+    Other(serde_json::Value),
+}
+
+impl Resource {
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Resource::Account(account) => Some(&account.id),
+            Resource::Category(category) => Some(&category.id),
+            Resource::Tag(tag) => Some(&tag.id),
+            Resource::Transaction(transaction) => Some(&transaction.id),
+            Resource::Other(value) => value.get("id").and_then(|id| id.as_str()),
+        }
+    }
+
+    pub fn resource_type(&self) -> Option<&str> {
+        match self {
+            Resource::Account(account) => Some(&account.resource_type),
+            Resource::Category(category) => Some(&category.resource_type),
+            Resource::Tag(tag) => Some(&tag.resource_type),
+            Resource::Transaction(transaction) => Some(&transaction.resource_type),
+            Resource::Other(value) => value.get("type").and_then(|t| t.as_str()),
+        }
+    }
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
-struct TagTransactionData {
-    #[serde(rename = "type")]
-    resource_type: String,
-    id: String,
+impl<'de> Deserialize<'de> for Resource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let resource = match value.get("type").and_then(|t| t.as_str()) {
+            Some("accounts") => {
+                Resource::Account(serde_json::from_value(value).map_err(de::Error::custom)?)
+            }
+            Some("categories") => {
+                Resource::Category(serde_json::from_value(value).map_err(de::Error::custom)?)
+            }
+            Some("tags") => {
+                Resource::Tag(serde_json::from_value(value).map_err(de::Error::custom)?)
+            }
+            Some("transactions") => {
+                Resource::Transaction(serde_json::from_value(value).map_err(de::Error::custom)?)
+            }
+            _ => Resource::Other(value),
+        };
+        Ok(resource)
+    }
+}
+
+/// The body of a request that updates a relationship,
+/// e.g. `{"data": {"type": "tags", "id": "Holiday"}}`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Payload<T> {
+    pub(crate) data: T,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Tag {
     #[serde(rename = "type")]
     pub resource_type: String,
@@ -128,33 +247,37 @@ pub struct Tag {
     pub relationships: TagRelationships,
 }
 
+impl PartialEq for Tag {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Tag {}
+
+impl Hash for Tag {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
 impl Tag {
     pub(crate) fn to_param(&self) -> &String {
         &self.id
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct TagRelationships {
     pub transactions: TagTransactionRelationships,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
-struct CategorizeTransaction {
-    data: CategorizeTransactionData,
-}
-
-#[derive(Serialize, Debug, PartialEq, Eq)]
-struct TagTransaction {
-    data: TagTransactionData,
-}
-
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct TagTransactionRelationships {
-    pub links: HashMap<String, String>,
+    pub links: RelationshipLinks,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Transaction {
     #[serde(rename = "type")]
     pub resource_type: String,
@@ -164,7 +287,75 @@ pub struct Transaction {
     pub links: Option<HashMap<String, String>>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+impl PartialEq for Transaction {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Transaction {}
+
+impl Hash for Transaction {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl PartialOrd for Transaction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Transaction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.attributes
+            .created_at
+            .cmp(&other.attributes.created_at)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+impl Transaction {
+    pub fn account_id(&self) -> &String {
+        &self.relationships.account.data.id
+    }
+
+    pub fn transfer_account_id(&self) -> Option<&String> {
+        self.relationships
+            .transfer_account
+            .data
+            .as_ref()
+            .map(|account| &account.id)
+    }
+
+    pub fn category_id(&self) -> Option<&String> {
+        self.relationships
+            .category
+            .data
+            .as_ref()
+            .map(|category| &category.id)
+    }
+
+    pub fn parent_category_id(&self) -> Option<&String> {
+        self.relationships
+            .parent_category
+            .data
+            .as_ref()
+            .map(|category| &category.id)
+    }
+
+    pub fn tag_ids(&self) -> Vec<&String> {
+        self.relationships
+            .tags
+            .data
+            .iter()
+            .map(|tag| &tag.id)
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct TransactionAttributes {
     pub status: TransactionStatus,
     #[serde(rename = "rawText")]
@@ -189,7 +380,9 @@ pub struct TransactionAttributes {
     pub created_at: DateTime<FixedOffset>,
 }
 
-#[derive(Deserialize, Debug, strum_macros::Display, PartialEq, Eq)]
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, strum_macros::Display, PartialEq, Eq, Hash,
+)]
 pub enum TransactionStatus {
     #[serde(rename = "HELD")]
     Held,
@@ -197,34 +390,36 @@ pub enum TransactionStatus {
     Settled,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct HoldInfo {
-    amount: Money,
+    pub amount: Money,
     #[serde(rename = "foreignAmount")]
-    foreign_amount: Option<Money>,
+    pub foreign_amount: Option<Money>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct RoundUp {
-    amount: Money,
+    pub amount: Money,
     #[serde(rename = "boostPortion")]
-    boost_portion: Option<Money>,
+    pub boost_portion: Option<Money>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Cashback {
-    description: String,
-    amount: Money,
+    pub description: String,
+    pub amount: Money,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct CardPurchaseMethod {
-    method: CardPurchaseMethodType,
+    pub method: CardPurchaseMethodType,
     #[serde(rename = "cardNumberSuffix")]
-    card_number_suffix: Option<String>,
+    pub card_number_suffix: Option<String>,
 }
 
-#[derive(Deserialize, Debug, strum_macros::Display, PartialEq, Eq)]
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, strum_macros::Display, PartialEq, Eq, Hash,
+)]
 pub enum CardPurchaseMethodType {
     #[serde(rename = "BAR_CODE")]
     BarCode,
@@ -244,7 +439,7 @@ pub enum CardPurchaseMethodType {
     Contactless,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct TransactionRelationships {
     pub account: TransactionAccountRelationship,
     #[serde(rename = "transferAccount")]
@@ -255,64 +450,176 @@ pub struct TransactionRelationships {
     pub tags: TransactionTagsRelationship,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct TransactionAccountRelationship {
-    pub data: TransactionAccountRelationshipData,
-    pub links: Option<HashMap<String, String>>,
+    pub data: ResourceIdentifier,
+    pub links: Option<RelationshipLinks>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-pub struct TransactionAccountRelationshipData {
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TransactionTransferAccountRelationship {
+    pub data: Option<ResourceIdentifier>,
+    pub links: Option<RelationshipLinks>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TransactionCategoryRelationship {
+    pub data: Option<ResourceIdentifier>,
+    pub links: Option<RelationshipLinks>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TransactionTagsRelationship {
+    pub data: Vec<ResourceIdentifier>,
+    pub links: Option<RelationshipLinks>,
+}
+
+/// The `meta` of the response to `/util/ping`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PingMeta {
+    pub id: String,
+    #[serde(rename = "statusEmoji")]
+    pub status_emoji: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Webhook {
     #[serde(rename = "type")]
     pub resource_type: String,
     pub id: String,
+    pub attributes: WebhookAttributes,
+    pub relationships: WebhookRelationships,
+    pub links: Option<HashMap<String, String>>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-pub struct TransactionTransferAccountRelationship {
-    pub data: Option<TransactionTransferAccountRelationshipData>,
-    pub links: Option<HashMap<String, String>>,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookAttributes {
+    pub url: String,
+    pub description: Option<String>,
+    /// The key used to sign the events sent to the webhook. Only returned when it is created.
+    #[serde(rename = "secretKey")]
+    pub secret_key: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<FixedOffset>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-pub struct TransactionTransferAccountRelationshipData {
-    #[serde(rename = "type")]
-    resource_type: String,
-    id: String,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookRelationships {
+    pub logs: WebhookLogsRelationship,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-pub struct TransactionCategoryRelationship {
-    pub data: Option<TransactionCategoryRelationshipData>,
-    pub links: Option<HashMap<String, String>>,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookLogsRelationship {
+    pub links: Option<RelationshipLinks>,
+}
+
+/// The body of a request that creates a webhook.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WebhookInput {
+    pub(crate) attributes: WebhookInputAttributes,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WebhookInputAttributes {
+    pub(crate) url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-pub struct TransactionCategoryRelationshipData {
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookEvent {
     #[serde(rename = "type")]
-    resource_type: String,
-    id: String,
+    pub resource_type: String,
+    pub id: String,
+    pub attributes: WebhookEventAttributes,
+    pub relationships: WebhookEventRelationships,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-pub struct TransactionTagsRelationship {
-    pub data: Vec<TransactionTagsRelationshipData>,
-    pub links: Option<HashMap<String, String>>,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookEventAttributes {
+    #[serde(rename = "eventType")]
+    pub event_type: WebhookEventType,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<FixedOffset>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-pub struct TransactionTagsRelationshipData {
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, strum_macros::Display, PartialEq, Eq, Hash,
+)]
+pub enum WebhookEventType {
+    #[serde(rename = "TRANSACTION_CREATED")]
+    TransactionCreated,
+    #[serde(rename = "TRANSACTION_SETTLED")]
+    TransactionSettled,
+    #[serde(rename = "TRANSACTION_DELETED")]
+    TransactionDeleted,
+    #[serde(rename = "PING")]
+    Ping,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookEventRelationships {
+    pub webhook: WebhookEventRelationship,
+    pub transaction: Option<WebhookEventRelationship>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookEventRelationship {
+    pub data: ResourceIdentifier,
+    pub links: Option<RelationshipLinks>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookDeliveryLog {
     #[serde(rename = "type")]
     pub resource_type: String,
     pub id: String,
+    pub attributes: WebhookDeliveryLogAttributes,
+    pub relationships: WebhookDeliveryLogRelationships,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookDeliveryLogAttributes {
+    pub request: WebhookDeliveryRequest,
+    /// The response of the webhook, if it responded at all.
+    pub response: Option<WebhookDeliveryResponse>,
+    #[serde(rename = "deliveryStatus")]
+    pub delivery_status: WebhookDeliveryStatus,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<FixedOffset>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookDeliveryRequest {
+    pub body: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookDeliveryResponse {
+    #[serde(rename = "statusCode")]
+    pub status_code: u16,
+    pub body: String,
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, strum_macros::Display, PartialEq, Eq, Hash,
+)]
+pub enum WebhookDeliveryStatus {
+    #[serde(rename = "DELIVERED")]
+    Delivered,
+    #[serde(rename = "UNDELIVERABLE")]
+    Undeliverable,
+    #[serde(rename = "BAD_RESPONSE_CODE")]
+    BadResponseCode,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-pub(crate) struct PingSuccessful {
-    meta: Option<HashMap<String, String>>,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookDeliveryLogRelationships {
+    #[serde(rename = "webhookEvent")]
+    pub webhook_event: WebhookDeliveryLogEventRelationship,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-pub(crate) struct PingNotAuthorized {
-    errors: Vec<HashMap<String, String>>,
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebhookDeliveryLogEventRelationship {
+    pub data: ResourceIdentifier,
 }
@@ -0,0 +1,594 @@
+use crate::api_endpoints::ListTransactions;
+use crate::client::UpClient;
+use crate::dates::{self, Tz, DEFAULT_TIMEZONE};
+use crate::models::{Transaction, TransactionStatus};
+use crate::reconcile::parse_amount;
+use crate::request_sender::ApiRequest;
+use crate::store::TransactionQuery;
+use chrono::{DateTime, Days, FixedOffset, NaiveDate};
+
+/// A filter over transactions, parsed from an expression such as
+/// `amount < -50 and description ~ 'uber' and not tag:'work' and card:1234 and foreign`.
+///
+/// Conditions are combined with `and`, `or`, `not` and parentheses:
+/// - `amount` compared with `<`, `<=`, `>`, `>=`, `=` or `!=` to an amount in dollars.
+/// - `date` compared in the same way to a day, e.g. `date >= 2022-10-01`.
+/// - `description`, `message` and `raw` (the raw text) with `~` or `:` for contains, or with `=`
+///   and `!=`, ignoring case.
+/// - `status:held`, `account:<id>`, `category:<id>` (which also matches the children of a parent
+///   category), `tag:<tag>` and `card:<last four digits>`.
+/// - The flags `foreign`, `held`, `settled`, `transfer` and `roundup`.
+///
+/// Values are quoted with `'` or `"` when they contain spaces or other symbols.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    expression: Expression,
+    timezone: Tz,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expression {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Predicate(Predicate),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Predicate {
+    Amount(Comparison, i128),
+    Date(Comparison, NaiveDate),
+    Text(TextField, TextMatch, String),
+    Status(TransactionStatus),
+    Account(String),
+    Category(String),
+    Tag(String),
+    Card(String),
+    Foreign,
+    Transfer,
+    RoundUp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextField {
+    Description,
+    Message,
+    RawText,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextMatch {
+    Contains,
+    Equal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    Colon,
+    Operator(&'static str),
+    Quoted(String),
+    Word(String),
+}
+
+impl Query {
+    /// Parses a query. Dates are compared in Australia/Melbourne unless `timezone` is set.
+    pub fn parse(query: &str) -> Result<Query, String> {
+        let tokens = tokenize(query)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+        };
+        let expression = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("Unexpected {} in the query.", describe(token)));
+        }
+        Ok(Query {
+            expression,
+            timezone: DEFAULT_TIMEZONE,
+        })
+    }
+
+    pub fn timezone(mut self, timezone: Tz) -> Query {
+        self.timezone = timezone;
+        self
+    }
+
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        self.evaluate(&self.expression, transaction)
+    }
+
+    /// The transactions that match, in the order given.
+    pub fn filter<'a, I>(&self, transactions: I) -> Vec<&'a Transaction>
+    where
+        I: IntoIterator<Item = &'a Transaction>,
+    {
+        transactions
+            .into_iter()
+            .filter(|transaction| self.matches(transaction))
+            .collect()
+    }
+
+    /// Adds the filters the API can apply itself (status, dates, account, category and tag) to
+    /// `request`. Only conditions that every match has to meet are pushed down, so the results
+    /// still have to be filtered with `matches`.
+    pub fn push_down(&self, request: ListTransactions) -> ListTransactions {
+        let filters = self.server_filters();
+        let mut request = request;
+        if let Some(account_id) = &filters.account_id {
+            request = request.account(account_id);
+        }
+        if let Some(status) = filters.status {
+            request = request.status(status);
+        }
+        if let Some(since) = filters.since {
+            request = request.since(since);
+        }
+        if let Some(until) = filters.until {
+            request = request.until(until);
+        }
+        if let Some(category_id) = &filters.category_id {
+            request = request.category_id(category_id);
+        }
+        if let Some(tag) = &filters.tag {
+            request = request.tag_id(tag);
+        }
+        request
+    }
+
+    /// The filters of `push_down` as a query for a `Store`.
+    pub fn store_query(&self) -> TransactionQuery {
+        self.server_filters()
+    }
+
+    /// Lists the transactions that match, letting the API do as much of the filtering as it can.
+    pub async fn fetch(&self, client: &UpClient) -> Result<Vec<Transaction>, String> {
+        let transactions = self.push_down(client.list_transactions()).send().await?;
+        Ok(transactions
+            .into_iter()
+            .filter(|transaction| self.matches(transaction))
+            .collect())
+    }
+
+    fn evaluate(&self, expression: &Expression, transaction: &Transaction) -> bool {
+        match expression {
+            Expression::And(left, right) => {
+                self.evaluate(left, transaction) && self.evaluate(right, transaction)
+            }
+            Expression::Or(left, right) => {
+                self.evaluate(left, transaction) || self.evaluate(right, transaction)
+            }
+            Expression::Not(expression) => !self.evaluate(expression, transaction),
+            Expression::Predicate(predicate) => self.test(predicate, transaction),
+        }
+    }
+
+    fn test(&self, predicate: &Predicate, transaction: &Transaction) -> bool {
+        let attributes = &transaction.attributes;
+        match predicate {
+            Predicate::Amount(comparison, amount) => {
+                comparison.test(&attributes.amount.value_in_base_units, amount)
+            }
+            Predicate::Date(comparison, date) => {
+                let created = attributes.created_at.with_timezone(&self.timezone);
+                comparison.test(&created.date_naive(), date)
+            }
+            Predicate::Text(field, text_match, value) => {
+                let text = match field {
+                    TextField::Description => Some(&attributes.description),
+                    TextField::Message => attributes.message.as_ref(),
+                    TextField::RawText => attributes.raw_text.as_ref(),
+                };
+                let text = text.map(|text| text.to_lowercase()).unwrap_or_default();
+                match text_match {
+                    TextMatch::Contains => text.contains(&value.to_lowercase()),
+                    TextMatch::Equal => text == value.to_lowercase(),
+                }
+            }
+            Predicate::Status(status) => attributes.status == *status,
+            Predicate::Account(account_id) => transaction.account_id() == account_id,
+            Predicate::Category(category_id) => {
+                transaction.category_id() == Some(category_id)
+                    || transaction.parent_category_id() == Some(category_id)
+            }
+            Predicate::Tag(tag) => transaction.tag_ids().contains(&tag),
+            Predicate::Card(suffix) => attributes
+                .card_purchase_method
+                .as_ref()
+                .and_then(|method| method.card_number_suffix.as_ref())
+                .is_some_and(|card_suffix| card_suffix == suffix),
+            Predicate::Foreign => attributes.foreign_amount.is_some(),
+            Predicate::Transfer => transaction.transfer_account_id().is_some(),
+            Predicate::RoundUp => attributes.round_up.is_some(),
+        }
+    }
+
+    /// The filters that every match has to meet, from the conditions joined by `and` at the top
+    /// of the query. Where there are several, the first account, status, category and tag and the
+    /// narrowest dates are used.
+    fn server_filters(&self) -> TransactionQuery {
+        let mut conditions = Vec::new();
+        conjuncts(&self.expression, &mut conditions);
+        let start = |date: NaiveDate| dates::start_of_day(date, self.timezone);
+        let next = |date: NaiveDate| date.checked_add_days(Days::new(1)).map(start);
+        let mut filters = TransactionQuery::new();
+        let mut since: Vec<DateTime<FixedOffset>> = Vec::new();
+        let mut until: Vec<DateTime<FixedOffset>> = Vec::new();
+        for predicate in conditions {
+            match predicate {
+                Predicate::Status(status) if filters.status.is_none() => {
+                    filters.status = Some(*status)
+                }
+                Predicate::Account(account_id) if filters.account_id.is_none() => {
+                    filters.account_id = Some(account_id.to_string())
+                }
+                Predicate::Category(category_id) if filters.category_id.is_none() => {
+                    filters.category_id = Some(category_id.to_string())
+                }
+                Predicate::Tag(tag) if filters.tag.is_none() => filters.tag = Some(tag.to_string()),
+                Predicate::Date(comparison, date) => match comparison {
+                    Comparison::Greater => since.extend(next(*date)),
+                    Comparison::GreaterOrEqual => since.push(start(*date)),
+                    Comparison::Less => until.push(start(*date)),
+                    Comparison::LessOrEqual => until.extend(next(*date)),
+                    Comparison::Equal => {
+                        since.push(start(*date));
+                        until.extend(next(*date));
+                    }
+                    Comparison::NotEqual => {}
+                },
+                _ => {}
+            }
+        }
+        filters.since = since.into_iter().max();
+        filters.until = until.into_iter().min();
+        filters
+    }
+}
+
+/// Collects the predicates joined by `and` at the top of `expression`.
+fn conjuncts<'a>(expression: &'a Expression, predicates: &mut Vec<&'a Predicate>) {
+    match expression {
+        Expression::And(left, right) => {
+            conjuncts(left, predicates);
+            conjuncts(right, predicates);
+        }
+        Expression::Predicate(predicate) => predicates.push(predicate),
+        Expression::Or(_, _) | Expression::Not(_) => {}
+    }
+}
+
+impl Comparison {
+    fn test<T: PartialOrd>(&self, value: &T, other: &T) -> bool {
+        match self {
+            Comparison::Less => value < other,
+            Comparison::LessOrEqual => value <= other,
+            Comparison::Greater => value > other,
+            Comparison::GreaterOrEqual => value >= other,
+            Comparison::Equal => value == other,
+            Comparison::NotEqual => value != other,
+        }
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Expression, String> {
+        let mut expression = self.and()?;
+        while self.keyword("or") {
+            expression = Expression::Or(Box::new(expression), Box::new(self.and()?));
+        }
+        Ok(expression)
+    }
+
+    fn and(&mut self) -> Result<Expression, String> {
+        let mut expression = self.not()?;
+        while self.keyword("and") {
+            expression = Expression::And(Box::new(expression), Box::new(self.not()?));
+        }
+        Ok(expression)
+    }
+
+    fn not(&mut self) -> Result<Expression, String> {
+        if self.keyword("not") {
+            return Ok(Expression::Not(Box::new(self.not()?)));
+        }
+        match self.next() {
+            Some(Token::Open) => {
+                let expression = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expression),
+                    _ => Err("A parenthesis is not closed.".to_string()),
+                }
+            }
+            Some(Token::Word(field)) => {
+                let field = field.to_lowercase();
+                self.condition(&field)
+            }
+            Some(token) => Err(format!("Expected a condition, found {}.", describe(token))),
+            None => Err("Expected a condition at the end of the query.".to_string()),
+        }
+    }
+
+    fn condition(&mut self, field: &str) -> Result<Expression, String> {
+        let operator = match self.peek() {
+            Some(Token::Colon) => ":",
+            Some(Token::Operator(operator)) => operator,
+            _ => return flag(field).map(Expression::Predicate),
+        };
+        self.position += 1;
+        let value = match self.next() {
+            Some(Token::Quoted(value)) | Some(Token::Word(value)) => value.to_string(),
+            _ => return Err(format!("Expected a value after {} {}.", field, operator)),
+        };
+        let comparison = match operator {
+            "<" => Some(Comparison::Less),
+            "<=" => Some(Comparison::LessOrEqual),
+            ">" => Some(Comparison::Greater),
+            ">=" => Some(Comparison::GreaterOrEqual),
+            "=" | ":" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            _ => None,
+        };
+        let invalid = || format!("{} can not be used with {}.", operator, field);
+        let predicate = match (field, comparison) {
+            ("amount", Some(comparison)) => Predicate::Amount(
+                comparison,
+                parse_amount(&value).ok_or_else(|| format!("{} is not an amount.", value))?,
+            ),
+            ("date", Some(comparison)) => Predicate::Date(
+                comparison,
+                NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+                    .map_err(|_| format!("{} is not a date, e.g. 2022-10-01.", value))?,
+            ),
+            ("description" | "message" | "raw", _) => {
+                let text_field = match field {
+                    "description" => TextField::Description,
+                    "message" => TextField::Message,
+                    _ => TextField::RawText,
+                };
+                let predicate = match operator {
+                    "~" | ":" => Predicate::Text(text_field, TextMatch::Contains, value),
+                    "=" | "!=" => Predicate::Text(text_field, TextMatch::Equal, value),
+                    _ => return Err(invalid()),
+                };
+                return Ok(negate(operator == "!=", predicate));
+            }
+            (_, Some(Comparison::Equal | Comparison::NotEqual)) => {
+                let predicate = match field {
+                    "status" => Predicate::Status(match value.to_lowercase().as_str() {
+                        "held" => TransactionStatus::Held,
+                        "settled" => TransactionStatus::Settled,
+                        _ => return Err(format!("{} is not held or settled.", value)),
+                    }),
+                    "account" => Predicate::Account(value),
+                    "category" => Predicate::Category(value),
+                    "tag" => Predicate::Tag(value),
+                    "card" => Predicate::Card(value),
+                    _ => return Err(unknown(field)),
+                };
+                return Ok(negate(operator == "!=", predicate));
+            }
+            ("amount" | "date" | "status" | "account" | "category" | "tag" | "card", _) => {
+                return Err(invalid())
+            }
+            _ => return Err(unknown(field)),
+        };
+        Ok(Expression::Predicate(predicate))
+    }
+}
+
+fn flag(field: &str) -> Result<Predicate, String> {
+    match field {
+        "foreign" => Ok(Predicate::Foreign),
+        "transfer" => Ok(Predicate::Transfer),
+        "roundup" => Ok(Predicate::RoundUp),
+        "held" => Ok(Predicate::Status(TransactionStatus::Held)),
+        "settled" => Ok(Predicate::Status(TransactionStatus::Settled)),
+        _ => Err(format!("Expected a condition, found {}.", field)),
+    }
+}
+
+fn negate(negated: bool, predicate: Predicate) -> Expression {
+    match negated {
+        true => Expression::Not(Box::new(Expression::Predicate(predicate))),
+        false => Expression::Predicate(predicate),
+    }
+}
+
+fn unknown(field: &str) -> String {
+    format!("{} is not a field that can be queried.", field)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Open => "(".to_string(),
+        Token::Close => ")".to_string(),
+        Token::Colon => ":".to_string(),
+        Token::Operator(operator) => operator.to_string(),
+        Token::Quoted(value) | Token::Word(value) => value.to_string(),
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ':' => Token::Colon,
+            '~' => Token::Operator("~"),
+            '=' => Token::Operator("="),
+            '<' | '>' | '!' => {
+                let equals = chars.next_if_eq(&'=').is_some();
+                match (c, equals) {
+                    ('<', false) => Token::Operator("<"),
+                    ('<', true) => Token::Operator("<="),
+                    ('>', false) => Token::Operator(">"),
+                    ('>', true) => Token::Operator(">="),
+                    ('!', true) => Token::Operator("!="),
+                    _ => return Err("Expected = after !.".to_string()),
+                }
+            }
+            '\'' | '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err("A quoted value is not closed.".to_string()),
+                        Some(end) if end == c => break,
+                        Some(other) => value.push(other),
+                    }
+                }
+                Token::Quoted(value)
+            }
+            _ => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| is_word(*c)) {
+                    word.push(c);
+                }
+                if !is_word(c) {
+                    return Err(format!("Unexpected {} in the query.", c));
+                }
+                Token::Word(word)
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | '$' | ',' | '/')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{CardPurchaseMethod, CardPurchaseMethodType};
+    use crate::query::*;
+    use crate::testing::{self, at, MockUpApi};
+
+    #[tokio::test]
+    async fn filters_and_pushes_down() {
+        let mut uber = testing::transaction(
+            "t1",
+            "spending",
+            "Uber Trip",
+            -6250,
+            at("2022-10-02T08:00:00+11:00"),
+        );
+        uber.attributes.foreign_amount = Some(testing::money(-4000));
+        uber.attributes.card_purchase_method = Some(CardPurchaseMethod {
+            method: CardPurchaseMethodType::Contactless,
+            card_number_suffix: Some("1234".to_string()),
+        });
+        let mut work_uber = uber.clone();
+        work_uber.id = "t2".to_string();
+        work_uber.relationships.tags.data = vec![testing::identifier("tags", "work")];
+        let mut cheap_uber = uber.clone();
+        cheap_uber.id = "t3".to_string();
+        cheap_uber.attributes.amount = testing::money(-1200);
+        let coffee = testing::transaction(
+            "t4",
+            "spending",
+            "Coffee",
+            -450,
+            at("2022-10-03T08:00:00+11:00"),
+        );
+        let transactions = vec![uber, work_uber, cheap_uber, coffee];
+
+        let query = Query::parse(
+            "amount < -50 and description ~ 'uber' and not tag:'work' and card:1234 and foreign",
+        )
+        .unwrap();
+        let ids: Vec<&str> = query
+            .filter(&transactions)
+            .iter()
+            .map(|transaction| transaction.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["t1"]);
+
+        let query = Query::parse("(description = coffee or amount >= -12) and date > 2022-10-01");
+        let ids: Vec<&str> = query
+            .unwrap()
+            .filter(&transactions)
+            .iter()
+            .map(|transaction| transaction.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["t3", "t4"]);
+
+        let query = Query::parse(
+            "settled and tag:work and date >= 2022-10-01 and date <= 2022-10-02 \
+             and (category:takeaway or foreign)",
+        )
+        .unwrap();
+        let filters = query.store_query();
+        assert_eq!(filters.status, Some(TransactionStatus::Settled));
+        assert_eq!(filters.tag.as_deref(), Some("work"));
+        assert_eq!(filters.category_id, None);
+        assert_eq!(filters.since, Some(at("2022-10-01T00:00:00+10:00")));
+        assert_eq!(filters.until, Some(at("2022-10-03T00:00:00+11:00")));
+
+        let mock = MockUpApi::new();
+        for transaction in &transactions {
+            mock.clone().transaction(transaction.clone());
+        }
+        let fetched = query.fetch(&mock.client()).await.unwrap();
+        assert_eq!(fetched.len(), 1);
+        assert_eq!(fetched[0].id, "t2");
+        let request = &mock.get_requests()[0];
+        assert!(request
+            .query
+            .contains(&("filter[tag]".to_string(), "work".to_string())));
+        assert!(request
+            .query
+            .contains(&("filter[status]".to_string(), "SETTLED".to_string())));
+
+        assert_eq!(
+            Query::parse("amount ~ 5").unwrap_err(),
+            "~ can not be used with amount."
+        );
+        assert_eq!(
+            Query::parse("colour:red").unwrap_err(),
+            "colour is not a field that can be queried."
+        );
+        assert!(Query::parse("(foreign").is_err());
+        assert!(Query::parse("description ~ 'uber").is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+/// The limits applied to the requests of an `UpClient` and all of its clones.
+///
+/// Requests are spaced out with a token bucket that refills at `requests_per_second` and holds up
+/// to `burst` requests. Every page of a paginated request counts as a request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    requests_per_second: f64,
+    burst: u32,
+    max_concurrency: Option<usize>,
+}
+
+impl RateLimit {
+    /// Allows `requests_per_second` requests per second, one at a time with no burst.
+    pub fn new(requests_per_second: f64) -> RateLimit {
+        RateLimit {
+            requests_per_second,
+            burst: 1,
+            max_concurrency: None,
+        }
+    }
+
+    /// Allows up to `burst` requests to be sent at once after the client has been idle.
+    pub fn burst(mut self, burst: u32) -> RateLimit {
+        if burst > 0 {
+            self.burst = burst;
+        } else {
+            eprintln!("Burst has to be at least 1.");
+        }
+        self
+    }
+
+    /// Allows at most `max_concurrency` requests to be in flight at once.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> RateLimit {
+        if max_concurrency > 0 {
+            self.max_concurrency = Some(max_concurrency);
+        } else {
+            eprintln!("Max concurrency has to be at least 1.");
+        }
+        self
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
+        self.requests_per_second.is_finite() && self.requests_per_second > 0.0
+    }
+}
+
+struct State {
+    tokens: f64,
+    refilled_at: Instant,
+    in_flight: usize,
+    waiting: Vec<Waker>,
+}
+
+/// Shared by an `UpClient` and its clones.
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    state: Mutex<State>,
+    released: Condvar,
+}
+
+/// A slot for one request. The slot is given back when the permit is dropped.
+pub(crate) struct Permit {
+    limiter: Arc<RateLimiter>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut state = self.limiter.lock();
+        state.in_flight -= 1;
+        for waker in state.waiting.drain(..) {
+            waker.wake();
+        }
+        self.limiter.released.notify_all();
+    }
+}
+
+/// Resolves once fewer than `max_concurrency` requests are in flight, taking a slot.
+struct Slot<'a> {
+    limiter: &'a RateLimiter,
+}
+
+impl Future for Slot<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.limiter.lock();
+        if self.limiter.has_free_slot(&state) {
+            state.in_flight += 1;
+            Poll::Ready(())
+        } else {
+            state.waiting.push(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> RateLimiter {
+        RateLimiter {
+            limit,
+            state: Mutex::new(State {
+                tokens: f64::from(limit.burst),
+                refilled_at: Instant::now(),
+                in_flight: 0,
+                waiting: Vec::new(),
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn has_free_slot(&self, state: &State) -> bool {
+        match self.limit.max_concurrency {
+            None => true,
+            Some(max_concurrency) => state.in_flight < max_concurrency,
+        }
+    }
+
+    /// Takes a token if one is available, or returns how long until one will be.
+    fn take_token(&self) -> Option<Duration> {
+        let mut state = self.lock();
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.limit.requests_per_second)
+            .min(f64::from(self.limit.burst));
+        state.refilled_at = now;
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64(
+                (1.0 - state.tokens) / self.limit.requests_per_second,
+            ))
+        }
+    }
+
+    /// Waits for a free slot and then for a token.
+    pub(crate) async fn acquire(self: &Arc<Self>) -> Permit {
+        Slot { limiter: self }.await;
+        let permit = Permit {
+            limiter: self.clone(),
+        };
+        while let Some(wait) = self.take_token() {
+            futures_timer::Delay::new(wait).await;
+        }
+        permit
+    }
+
+    /// The blocking counterpart of `acquire`.
+    #[cfg(feature = "blocking")]
+    pub(crate) fn acquire_blocking(self: &Arc<Self>) -> Permit {
+        let mut state = self.lock();
+        while !self.has_free_slot(&state) {
+            state = self.released.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        state.in_flight += 1;
+        drop(state);
+        let permit = Permit {
+            limiter: self.clone(),
+        };
+        while let Some(wait) = self.take_token() {
+            std::thread::sleep(wait);
+        }
+        permit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::UpClient;
+    use crate::models::AccountType;
+    use crate::rate_limit::*;
+    use crate::request_sender::ApiRequest;
+    use crate::testing::{self, MockUpApi};
+    use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn spaces_out_requests_after_the_burst() {
+        let mock = MockUpApi::new().account(testing::account("a", "A", AccountType::Saver, 1));
+        let client = mock.client().rate_limit(RateLimit::new(20.0).burst(2));
+        let started = Instant::now();
+        for _ in 0..5 {
+            client.list_accounts().send().await.unwrap();
+        }
+        // Two requests use the burst and the other three wait 50ms each.
+        assert!(started.elapsed() >= Duration::from_millis(140));
+    }
+
+    struct Slow {
+        in_flight: AtomicUsize,
+        most_in_flight: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl HttpTransport for Slow {
+        async fn send(&self, _request: HttpRequest) -> Result<HttpResponse, String> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.most_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            futures_timer::Delay::new(Duration::from_millis(20)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: r#"{"data": [], "links": {"prev": null, "next": null}}"#.to_string(),
+            })
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn clones_share_the_concurrency_limit() {
+        let most_in_flight = Arc::new(AtomicUsize::new(0));
+        let client = UpClient::new(&"token".to_string())
+            .transport(Slow {
+                in_flight: AtomicUsize::new(0),
+                most_in_flight: most_in_flight.clone(),
+            })
+            .rate_limit(RateLimit::new(1000.0).burst(100).max_concurrency(2));
+        let handles: Vec<_> = (0..6)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.list_tags().send().await })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+        assert_eq!(most_in_flight.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,353 @@
+use crate::dates::{Tz, DEFAULT_TIMEZONE};
+use crate::export::csv;
+use crate::models::Transaction;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+
+/// The date formats tried, in order, when a `StatementReader` is not given one.
+const DATE_FORMATS: [&str; 4] = [
+    "%Y-%m-%d",
+    "%d/%m/%Y",
+    "%Y-%m-%d %H:%M:%S",
+    "%d/%m/%Y %H:%M",
+];
+
+/// A row of a statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementRow {
+    /// The transaction id, which only our own exports have.
+    pub id: Option<String>,
+    pub date: NaiveDate,
+    pub amount_in_base_units: i128,
+    pub description: String,
+}
+
+/// Reads the rows of a CSV statement, either one exported from the Up app or one written by
+/// `CsvWriter` with at least the `Date` and `Amount` columns.
+///
+/// Columns are found by their header. The amount is read from `Amount`, or from `Subtotal (AUD)`
+/// in Up's exports, as that is the amount without the round-up, like `Transaction::amount`.
+pub struct StatementReader {
+    date_format: Option<String>,
+}
+
+impl StatementReader {
+    pub fn new() -> StatementReader {
+        StatementReader { date_format: None }
+    }
+
+    /// A `chrono` format string for the dates, for when they are in none of the usual formats.
+    pub fn date_format(mut self, date_format: &str) -> StatementReader {
+        self.date_format = Some(date_format.to_string());
+        self
+    }
+
+    pub fn read(&self, csv: &str) -> Result<Vec<StatementRow>, String> {
+        let mut records = csv::parse(csv.trim_start_matches('\u{feff}'))?.into_iter();
+        let header = records
+            .next()
+            .ok_or_else(|| "The statement is empty.".to_string())?;
+        let column = |names: &[&str]| {
+            names.iter().find_map(|name| {
+                header
+                    .iter()
+                    .position(|column| column.trim().eq_ignore_ascii_case(name))
+            })
+        };
+        let id = column(&["Id", "Transaction Id"]);
+        let date = column(&["Date", "Time"])
+            .ok_or_else(|| "The statement has no Date or Time column.".to_string())?;
+        let amount = column(&["Amount", "Subtotal (AUD)", "Total (AUD)"])
+            .ok_or_else(|| "The statement has no Amount column.".to_string())?;
+        let description = column(&["Payee", "Description"]);
+
+        let mut rows = Vec::new();
+        for (line, record) in records.enumerate() {
+            if record.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+            let field = |index: usize| record.get(index).map_or("", |field| field.trim());
+            let row = line + 2;
+            rows.push(StatementRow {
+                id: id.map(field).filter(|id| !id.is_empty()).map(String::from),
+                date: self
+                    .parse_date(field(date))
+                    .ok_or_else(|| format!("Row {} has an invalid date: {}", row, field(date)))?,
+                amount_in_base_units: parse_amount(field(amount)).ok_or_else(|| {
+                    format!("Row {} has an invalid amount: {}", row, field(amount))
+                })?,
+                description: description.map(field).unwrap_or_default().to_string(),
+            });
+        }
+        Ok(rows)
+    }
+
+    fn parse_date(&self, date: &str) -> Option<NaiveDate> {
+        let parse = |format: &str| {
+            NaiveDate::parse_from_str(date, format)
+                .or_else(|_| NaiveDateTime::parse_from_str(date, format).map(|at| at.date()))
+                .ok()
+        };
+        match &self.date_format {
+            Some(format) => parse(format),
+            None => DATE_FORMATS
+                .iter()
+                .find_map(|format| parse(format))
+                .or_else(|| {
+                    DateTime::parse_from_rfc3339(date)
+                        .ok()
+                        .map(|at| at.date_naive())
+                }),
+        }
+    }
+}
+
+impl Default for StatementReader {
+    fn default() -> Self {
+        StatementReader::new()
+    }
+}
+
+/// A statement row and the transaction it was matched to, whose amounts differ.
+#[derive(Debug, Clone)]
+pub struct Mismatch {
+    pub row: StatementRow,
+    pub transaction: Transaction,
+}
+
+/// The differences between a statement and the transactions from the API.
+#[derive(Debug, Clone, Default)]
+pub struct Reconciliation {
+    pub matched: usize,
+    pub missing_from_api: Vec<StatementRow>,
+    pub missing_from_statement: Vec<Transaction>,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl Reconciliation {
+    pub fn is_reconciled(&self) -> bool {
+        self.missing_from_api.is_empty()
+            && self.missing_from_statement.is_empty()
+            && self.mismatches.is_empty()
+    }
+}
+
+/// Reconciles the rows of a statement against transactions, dating the transactions in
+/// Australia/Melbourne.
+///
+/// Rows with an id are matched to the transaction with that id. Other rows are matched to a
+/// transaction on the same day with the same amount, or failing that the same description, which
+/// is reported as a mismatch.
+pub fn reconcile(rows: &[StatementRow], transactions: &[Transaction]) -> Reconciliation {
+    reconcile_in(rows, transactions, DEFAULT_TIMEZONE)
+}
+
+/// Like `reconcile`, dating the transactions in `timezone`.
+pub fn reconcile_in(
+    rows: &[StatementRow],
+    transactions: &[Transaction],
+    timezone: Tz,
+) -> Reconciliation {
+    let mut unmatched: Vec<Option<&Transaction>> = transactions.iter().map(Some).collect();
+    let by_id: HashMap<&String, usize> = transactions
+        .iter()
+        .enumerate()
+        .map(|(index, transaction)| (&transaction.id, index))
+        .collect();
+    let date = |transaction: &Transaction| {
+        transaction
+            .attributes
+            .created_at
+            .with_timezone(&timezone)
+            .date_naive()
+    };
+    let mut reconciliation = Reconciliation::default();
+    let mut record = |row: &StatementRow, transaction: &Transaction| {
+        if transaction.attributes.amount.value_in_base_units == row.amount_in_base_units {
+            reconciliation.matched += 1;
+        } else {
+            reconciliation.mismatches.push(Mismatch {
+                row: row.clone(),
+                transaction: transaction.clone(),
+            });
+        }
+    };
+
+    let mut without_id = Vec::new();
+    let mut missing = Vec::new();
+    for row in rows {
+        let index = match &row.id {
+            None => {
+                without_id.push(row);
+                continue;
+            }
+            Some(id) => by_id.get(id),
+        };
+        match index.and_then(|index| unmatched[*index].take()) {
+            Some(transaction) => record(row, transaction),
+            None => missing.push(row),
+        }
+    }
+    let mut take = |row: &StatementRow, same: &dyn Fn(&Transaction) -> bool| {
+        let position = unmatched.iter().position(|transaction| {
+            transaction
+                .is_some_and(|transaction| date(transaction) == row.date && same(transaction))
+        })?;
+        unmatched[position].take()
+    };
+    let mut unpaired = Vec::new();
+    for row in without_id {
+        let same_amount = |transaction: &Transaction| {
+            transaction.attributes.amount.value_in_base_units == row.amount_in_base_units
+        };
+        match take(row, &same_amount) {
+            Some(transaction) => record(row, transaction),
+            None => unpaired.push(row),
+        }
+    }
+    for row in unpaired {
+        let same_description = |transaction: &Transaction| {
+            !row.description.is_empty() && transaction.attributes.description == row.description
+        };
+        match take(row, &same_description) {
+            Some(transaction) => record(row, transaction),
+            None => missing.push(row),
+        }
+    }
+
+    reconciliation.missing_from_api = missing.into_iter().cloned().collect();
+    reconciliation.missing_from_statement = unmatched.into_iter().flatten().cloned().collect();
+    reconciliation
+}
+
+/// Parses an amount such as `-12.34`, `$1,234.5`, `-$0.66` or `$-0.66` into base units. An amount
+/// in parentheses, e.g. `(12.34)`, is negative, as in accounting statements.
+pub(crate) fn parse_amount(amount: &str) -> Option<i128> {
+    let (parenthesized, amount) = match amount.strip_prefix('(') {
+        Some(amount) => (true, amount.strip_suffix(')')?.trim()),
+        None => (false, amount),
+    };
+    let (minus_before, amount) = strip_minus(amount);
+    let amount = amount.strip_prefix('$').unwrap_or(amount);
+    let (minus_after, amount) = strip_minus(amount);
+    if usize::from(parenthesized) + usize::from(minus_before) + usize::from(minus_after) > 1 {
+        return None;
+    }
+    let amount = amount.replace(',', "");
+    let (whole, fraction) = amount.split_once('.').unwrap_or((&amount, ""));
+    if whole.is_empty() && fraction.is_empty() || fraction.len() > 2 {
+        return None;
+    }
+    let digits = |text: &str| text.chars().all(|c| c.is_ascii_digit());
+    if !digits(whole) || !digits(fraction) {
+        return None;
+    }
+    let whole: i128 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().ok()?
+    };
+    let fraction: i128 = format!("{:0<2}", fraction).parse().ok()?;
+    let base_units = whole.checked_mul(100)?.checked_add(fraction)?;
+    match parenthesized || minus_before || minus_after {
+        true => Some(-base_units),
+        false => Some(base_units),
+    }
+}
+
+fn strip_minus(amount: &str) -> (bool, &str) {
+    match amount.strip_prefix('-') {
+        Some(amount) => (true, amount),
+        None => (false, amount),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::csv::{Column, CsvWriter};
+    use crate::reconcile::*;
+    use crate::testing::{self, at};
+
+    #[test]
+    fn reports_missing_rows_and_mismatched_amounts() {
+        let transactions = vec![
+            testing::transaction(
+                "t1",
+                "spending",
+                "Pizza Hut",
+                -2345,
+                at("2022-10-01T23:30:00Z"),
+            ),
+            testing::transaction(
+                "t2",
+                "spending",
+                "Salary",
+                500000,
+                at("2022-10-03T09:00:00+11:00"),
+            ),
+            testing::transaction(
+                "t3",
+                "spending",
+                "Cafe",
+                -450,
+                at("2022-10-04T09:00:00+11:00"),
+            ),
+            testing::transaction(
+                "t4",
+                "spending",
+                "Refund",
+                1000,
+                at("2022-10-05T09:00:00+11:00"),
+            ),
+        ];
+
+        let exported = CsvWriter::new()
+            .columns(&[
+                Column::Id,
+                Column::Date,
+                Column::Description,
+                Column::Amount,
+            ])
+            .to_csv(&transactions[..2]);
+        let rows = StatementReader::new().read(&exported).unwrap();
+        assert_eq!(rows[0].id.as_deref(), Some("t1"));
+        assert_eq!(rows[0].date, NaiveDate::from_ymd_opt(2022, 10, 2).unwrap());
+        let reconciliation = reconcile(&rows, &transactions[..2]);
+        assert_eq!(reconciliation.matched, 2);
+        assert!(reconciliation.is_reconciled());
+
+        let statement =
+            "\u{feff}Time,Payee,Description,Subtotal (AUD),Round Up (AUD),Total (AUD)\r\n\
+            2022-10-02 10:30:00,Pizza Hut,\"Pizza Hut, Sydney\",-23.45,-0.55,-24.00\r\n\
+            2022-10-03 09:00:00,Salary,Salary,\"$5,000.00\",,\"$5,000.00\"\r\n\
+            2022-10-04 09:00:00,Cafe,Cafe,-5.00,,-5.00\r\n\
+            2022-10-06 12:00:00,Rent,Rent,-400,,-400\r\n";
+        let rows = StatementReader::new().read(statement).unwrap();
+        assert_eq!(rows[1].amount_in_base_units, 500000);
+        let reconciliation = reconcile(&rows, &transactions);
+        assert_eq!(reconciliation.matched, 2);
+        assert_eq!(reconciliation.mismatches.len(), 1);
+        assert_eq!(reconciliation.mismatches[0].transaction.id, "t3");
+        assert_eq!(reconciliation.mismatches[0].row.amount_in_base_units, -500);
+        assert_eq!(reconciliation.missing_from_api.len(), 1);
+        assert_eq!(reconciliation.missing_from_api[0].description, "Rent");
+        assert_eq!(reconciliation.missing_from_statement.len(), 1);
+        assert_eq!(reconciliation.missing_from_statement[0].id, "t4");
+
+        let error = StatementReader::new().read("Date,Amount\n2022-10-02,12.345\n");
+        assert_eq!(error.unwrap_err(), "Row 2 has an invalid amount: 12.345");
+    }
+
+    #[test]
+    fn parses_amounts() {
+        assert_eq!(parse_amount("-$1,234.5"), Some(-123450));
+        assert_eq!(parse_amount("$-12.34"), Some(-1234));
+        assert_eq!(parse_amount("(12.34)"), Some(-1234));
+        assert_eq!(parse_amount(".5"), Some(50));
+        assert_eq!(parse_amount("--1"), None);
+        assert_eq!(parse_amount("(-1)"), None);
+        assert_eq!(parse_amount("(1"), None);
+        assert_eq!(parse_amount(&"9".repeat(38)), None);
+        assert_eq!(parse_amount(&"9".repeat(40)), None);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::api_endpoints::*;
+use crate::client::UpClient;
+use crate::models::*;
+use crate::request_sender::ApiRequest;
+
+/// Returns the `related` link of a relationship, or builds the URL from `fallback` when the API
+/// has left it out.
+fn related_url<F: FnOnce() -> String>(links: Option<&RelationshipLinks>, fallback: F) -> String {
+    match links.and_then(|links| links.related.as_ref()) {
+        None => fallback(),
+        Some(related) => related.to_string(),
+    }
+}
+
+impl Account {
+    /// Retrieves every transaction belonging to this account.
+    pub async fn transactions(&self, client: &UpClient) -> Result<Vec<Transaction>, String> {
+        let url = related_url(Some(&self.relationships.transactions.links), || {
+            format!("{}/accounts/{}/transactions", BASE_URL, self.id)
+        });
+        ListTransactions::from_url(client, url)
+            .send()
+            .await
+    }
+}
+
+impl Transaction {
+    /// Retrieves the account this transaction belongs to.
+    pub async fn account(&self, client: &UpClient) -> Result<Account, String> {
+        let relationship = &self.relationships.account;
+        let url = related_url(relationship.links.as_ref(), || {
+            format!("{}/accounts/{}", BASE_URL, relationship.data.id)
+        });
+        RetrieveAccount::from_url(client, url)
+            .send()
+            .await
+    }
+
+    /// Retrieves the account on the other side of a transfer, if this transaction is one.
+    pub async fn transfer_account(&self, client: &UpClient) -> Result<Option<Account>, String> {
+        let relationship = &self.relationships.transfer_account;
+        match &relationship.data {
+            None => Ok(None),
+            Some(data) => {
+                let url = related_url(relationship.links.as_ref(), || {
+                    format!("{}/accounts/{}", BASE_URL, data.id)
+                });
+                RetrieveAccount::from_url(client, url)
+                    .send()
+                    .await
+                    .map(Some)
+            }
+        }
+    }
+
+    /// Retrieves the category of this transaction, if it has been categorized.
+    pub async fn category(&self, client: &UpClient) -> Result<Option<Category>, String> {
+        retrieve_category(client, &self.relationships.category).await
+    }
+
+    /// Retrieves the parent of this transaction's category, if it has been categorized.
+    pub async fn parent_category(&self, client: &UpClient) -> Result<Option<Category>, String> {
+        retrieve_category(client, &self.relationships.parent_category).await
+    }
+}
+
+async fn retrieve_category(
+    client: &UpClient,
+    relationship: &TransactionCategoryRelationship,
+) -> Result<Option<Category>, String> {
+    match &relationship.data {
+        None => Ok(None),
+        Some(data) => {
+            let url = related_url(relationship.links.as_ref(), || {
+                format!("{}/categories/{}", BASE_URL, data.id)
+            });
+            RetrieveCategory::from_url(client, url)
+                .send()
+                .await
+                .map(Some)
+        }
+    }
+}
+
+impl Category {
+    /// Retrieves the parent of this category, or `None` if it is a top level category.
+    pub async fn parent(&self, client: &UpClient) -> Result<Option<Category>, String> {
+        let relationship = &self.relationships.parent;
+        match &relationship.data {
+            None => Ok(None),
+            Some(data) => {
+                let url = related_url(relationship.links.as_ref(), || {
+                    format!("{}/categories/{}", BASE_URL, data.id)
+                });
+                RetrieveCategory::from_url(client, url)
+                    .send()
+                    .await
+                    .map(Some)
+            }
+        }
+    }
+
+    /// Retrieves the children of this category.
+    pub async fn children(&self, client: &UpClient) -> Result<Vec<Category>, String> {
+        let links = self.relationships.children.links.as_ref();
+        // Without a link the id goes in the query, which encodes it like any other filter.
+        let request = match links.and_then(|links| links.related.clone()) {
+            Some(url) => ListCategories::from_url(client, url),
+            None => client.list_categories().parent_id(&self.id),
+        };
+        request.send().await
+    }
+}
+
+impl Tag {
+    /// Retrieves every transaction with this tag.
+    pub async fn transactions(&self, client: &UpClient) -> Result<Vec<Transaction>, String> {
+        // Tags are free text, so without a link the tag goes in the query to be encoded.
+        let request = match &self.relationships.transactions.links.related {
+            Some(url) => ListTransactions::from_url(client, url.to_string()),
+            None => client.list_transactions().tag_id(&self.id),
+        };
+        request.send().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::*;
+    use crate::relationships::related_url;
+    use crate::testing::{self, MockUpApi};
+
+    #[test]
+    fn related_url_prefers_link() {
+        let links = RelationshipLinks {
+            self_: None,
+            related: Some("https://api.up.com.au/api/v1/categories/good-life".to_string()),
+        };
+        assert_eq!(
+            related_url(Some(&links), || "fallback".to_string()),
+            "https://api.up.com.au/api/v1/categories/good-life"
+        );
+    }
+
+    #[test]
+    fn related_url_falls_back_without_link() {
+        let links = RelationshipLinks {
+            self_: Some(
+                "https://api.up.com.au/api/v1/transactions/1/relationships/category".to_string(),
+            ),
+            related: None,
+        };
+        assert_eq!(
+            related_url(Some(&links), || "fallback".to_string()),
+            "fallback"
+        );
+        assert_eq!(related_url(None, || "fallback".to_string()), "fallback");
+    }
+
+    #[tokio::test]
+    async fn encodes_the_tag_without_a_link() {
+        let mut pizza = testing::transaction(
+            "t1",
+            "spending",
+            "Pizza",
+            -2000,
+            testing::at("2022-10-01T19:00:00+10:00"),
+        );
+        pizza.relationships.tags.data = vec![testing::identifier("tags", "Pizza & Wine #1")];
+        let mock = MockUpApi::new().transaction(pizza);
+        let tag = Tag {
+            resource_type: "tags".to_string(),
+            id: "Pizza & Wine #1".to_string(),
+            relationships: TagRelationships {
+                transactions: TagTransactionRelationships {
+                    links: RelationshipLinks {
+                        self_: None,
+                        related: None,
+                    },
+                },
+            },
+        };
+        let transactions = tag.transactions(&mock.client()).await.unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(
+            mock.get_requests()[0].query,
+            vec![("filter[tag]".to_string(), "Pizza & Wine #1".to_string())]
+        );
+    }
+}
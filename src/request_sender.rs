@@ -1,117 +1,309 @@
-use std::collections::HashMap;
+use crate::cancel::{interruptible, CancelToken, Deadline, Interrupted};
+use crate::client::UpClient;
+use crate::included::IncludedResources;
+use crate::models::Resource;
+use crate::token::Token;
+use crate::trace::Trace;
+use crate::transport::{HttpRequest, Method};
 use async_trait::async_trait;
-use reqwest::StatusCode;
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use std::collections::HashMap;
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct ApiResponse<T> {
     pub data: T,
     pub links: Option<HashMap<String, Option<String>>>,
+    #[serde(default)]
+    pub meta: Option<serde_json::Value>,
+    #[serde(default)]
+    pub included: Option<Vec<Resource>>,
 }
 
 impl<T> ApiResponse<T> {
-    pub(crate) fn has_next(api_response: &ApiResponse<T>) -> bool {
-        return match &api_response.links {
-            None => false,
-            Some(v) => match v.get("next") {
-                None => false,
-                Some(v2) => v2.is_some(),
-            },
-        };
+    pub(crate) fn next_link(&self) -> Option<String> {
+        self.links
+            .as_ref()
+            .and_then(|links| links.get("next"))
+            .and_then(|next| next.to_owned())
+    }
+
+    /// Builds an identity map over the resources in `included`, for resolving the relationships
+    /// of `data` without extra requests.
+    pub fn included_resources(&self) -> IncludedResources {
+        match &self.included {
+            None => IncludedResources::new(Vec::new()),
+            Some(included) => IncludedResources::new(included.to_vec()),
+        }
+    }
+}
+
+impl<T> ApiResponse<Vec<T>> {
+    /// Adds the next page of a paginated response. `data` and `included` are appended, while
+    /// `links` and `meta` are replaced by those of the page.
+    pub(crate) fn append_page(&mut self, mut page: ApiResponse<Vec<T>>) {
+        self.data.append(&mut page.data);
+        if let Some(mut included) = page.included.take() {
+            self.included
+                .get_or_insert_with(Vec::new)
+                .append(&mut included);
+        }
+        self.links = page.links;
+        self.meta = page.meta;
+    }
+}
+
+/// The URL, query parameters and credentials of a request. These are shared by the async
+/// `ApiRequest` and, with the `blocking` feature, `blocking::ApiRequest`.
+pub trait ApiEndpoint {
+    fn get_url(&self) -> &String;
+    fn get_params(&self) -> &Vec<(String, String)>;
+    fn get_token(&self) -> &Token;
+    fn get_client(&self) -> &UpClient;
+
+    fn get_method(&self) -> Method {
+        Method::Get
+    }
+
+    /// The JSON body sent with the request, if any.
+    fn get_body(&self) -> Option<&String> {
+        None
     }
 }
 
+/// Builds the first request of an endpoint. Following pages are plain `GET`s of the `next` link.
+pub(crate) fn endpoint_request<K: ApiEndpoint>(base: &K) -> HttpRequest {
+    let request = HttpRequest::new(base.get_method(), base.get_url(), base.get_token())
+        .query(base.get_params());
+    match base.get_body() {
+        None => request,
+        Some(body) => request.body(body.to_string()),
+    }
+}
+
+/// Turns a response with only `meta` into a document whose `data` is the `meta`.
+pub(crate) fn meta_document<T: DeserializeOwned>(
+    body: serde_json::Value,
+) -> Result<ApiResponse<T>, String> {
+    let meta = body
+        .get("meta")
+        .cloned()
+        .ok_or_else(|| "The response has no meta.".to_string())?;
+    Ok(ApiResponse {
+        data: serde_json::from_value(meta.clone()).map_err(|e| e.to_string())?,
+        links: None,
+        meta: Some(meta),
+        included: None,
+    })
+}
+
 #[async_trait]
-pub trait ApiRequest {
+pub trait ApiRequest: ApiEndpoint {
     type T;
     async fn send(self) -> Result<Self::T, String>;
-    fn get_url(&self) -> &String;
-    fn get_params(&self) -> &Vec<(String, String)>;
-    fn get_api_key(&self) -> &String;
+    /// Sends the request and returns the whole response document, including `meta` and
+    /// `included` alongside `data`.
+    async fn send_document(self) -> Result<ApiResponse<Self::T>, String>;
+}
+
+/// A request whose response is spread over pages.
+#[async_trait]
+pub trait PaginatedRequest: ApiEndpoint {
+    type Item;
+    /// Fetches every page like `ApiRequest::send`, but stops once `cancel` is cancelled or the
+    /// `total` timeout passes. The items of the pages fetched by then are returned with the error.
+    async fn send_cancellable(
+        self,
+        cancel: &CancelToken,
+    ) -> Result<Vec<Self::Item>, Interrupted<Self::Item>>;
 }
 
 pub(crate) struct RequestSender {}
 
 impl RequestSender {
-    pub(crate) async fn send_paginate<T: DeserializeOwned, K: ApiRequest>(
+    pub(crate) async fn send_paginate<T: DeserializeOwned, K: ApiEndpoint>(
         base: K,
     ) -> Result<Vec<T>, String> {
-        let client = reqwest::Client::new();
-        let res = client
-            .get(base.get_url())
-            .bearer_auth(base.get_api_key())
-            .query(base.get_params())
-            .send()
+        RequestSender::send_paginate_document::<T, K>(base)
             .await
-            .unwrap();
-
-        return match res.status() {
-            StatusCode::OK => {
-                let mut rtn: Vec<T> = Vec::new();
-                let mut json: ApiResponse<Vec<T>> = res.json().await.unwrap();
-                rtn.append(&mut json.data);
-                while ApiResponse::has_next(&json) {
-                    let next = json
-                        .links
-                        .as_ref()
-                        .unwrap()
-                        .get("next")
-                        .unwrap()
-                        .to_owned()
-                        .unwrap();
-                    let client = reqwest::Client::new();
-                    let res = client
-                        .get(next)
-                        .bearer_auth(base.get_api_key())
-                        .send()
-                        .await
-                        .unwrap();
-                    match res.status() {
-                        StatusCode::OK => {
-                            json = res.json().await.unwrap();
-                            rtn.append(&mut json.data);
-                        }
-                        _ => {
-                            return Err(res.text().await.unwrap());
-                        }
-                    }
-                }
-                Ok(rtn)
-            }
-            _ => Err(res.text().await.unwrap()),
+            .map(|document| document.data)
+    }
+
+    /// Follows the `next` links until every page has been fetched. The `data` and `included`
+    /// arrays of each page are concatenated and `links`/`meta` are taken from the last page.
+    pub(crate) async fn send_paginate_document<T: DeserializeOwned, K: ApiEndpoint>(
+        base: K,
+    ) -> Result<ApiResponse<Vec<T>>, String> {
+        RequestSender::send_paginate_interruptible::<T, K>(base, None)
+            .await
+            .map_err(|interrupted| interrupted.error)
+    }
+
+    pub(crate) async fn send_paginate_cancellable<T: DeserializeOwned, K: ApiEndpoint>(
+        base: K,
+        cancel: &CancelToken,
+    ) -> Result<Vec<T>, Interrupted<T>> {
+        RequestSender::send_paginate_interruptible::<T, K>(base, Some(cancel))
+            .await
+            .map(|document| document.data)
+    }
+
+    async fn send_paginate_interruptible<T: DeserializeOwned, K: ApiEndpoint>(
+        base: K,
+        cancel: Option<&CancelToken>,
+    ) -> Result<ApiResponse<Vec<T>>, Interrupted<T>> {
+        let client = base.get_client();
+        let request = endpoint_request(&base);
+        let trace = Trace::request(&request);
+        let pages = Pages {
+            client,
+            token: base.get_token(),
+            trace: &trace,
+            deadline: Deadline::start(&client.get_timeouts()),
+            cancel,
         };
+        let result = pages.fetch(request).await;
+        let outcome = result.as_ref().map(|document| document.data.len());
+        trace.finish(
+            &outcome.map_err(|interrupted| interrupted.error.clone()),
+            outcome.unwrap_or(0),
+        );
+        result
+    }
+
+    pub async fn send<T: DeserializeOwned, K: ApiEndpoint>(base: K) -> Result<T, String> {
+        RequestSender::send_document::<T, K>(base)
+            .await
+            .map(|document| document.data)
+    }
+
+    pub async fn send_document<T: DeserializeOwned, K: ApiEndpoint>(
+        base: K,
+    ) -> Result<ApiResponse<T>, String> {
+        let request = endpoint_request(&base);
+        let trace = Trace::request(&request);
+        let client = base.get_client();
+        let deadline = Deadline::start(&client.get_timeouts());
+        let response = interruptible(client.send(request), deadline, None).await;
+        trace.response(&response);
+        let result = response.and_then(|response| response.parse());
+        trace.finish(&result, 1);
+        result
     }
 
-    pub async fn send<T: DeserializeOwned, K: ApiRequest>(base: K) -> Result<T, String> {
-        let client = reqwest::Client::new();
-        let res = client
-            .get(base.get_url())
-            .bearer_auth(base.get_api_key())
-            .query(base.get_params())
-            .send()
+    pub async fn send_no_content<K: ApiEndpoint>(base: K) -> Result<(), String> {
+        RequestSender::send_no_content_document::<K>(base)
             .await
-            .unwrap();
+            .map(|document| document.data)
+    }
 
-        return match res.status() {
-            StatusCode::OK => {
-                let json: ApiResponse<T> = res.json().await.unwrap();
-                Ok(json.data)
-            }
-            _ => Err(res.text().await.unwrap()),
+    /// Sends a request that is answered with `204 No Content`. The returned document is empty.
+    pub async fn send_no_content_document<K: ApiEndpoint>(
+        base: K,
+    ) -> Result<ApiResponse<()>, String> {
+        let request = endpoint_request(&base);
+        let trace = Trace::request(&request);
+        let client = base.get_client();
+        let deadline = Deadline::start(&client.get_timeouts());
+        let response = interruptible(client.send(request), deadline, None).await;
+        trace.response(&response);
+        let result = response.and_then(|response| response.no_content());
+        trace.finish(&result, 0);
+        result?;
+        Ok(ApiResponse {
+            data: (),
+            links: None,
+            meta: None,
+            included: None,
+        })
+    }
+
+    pub async fn send_meta<T: DeserializeOwned, K: ApiEndpoint>(base: K) -> Result<T, String> {
+        RequestSender::send_meta_document::<T, K>(base)
+            .await
+            .map(|document| document.data)
+    }
+
+    /// Sends a request that is answered with only `meta`, e.g. `/util/ping`. The `meta` is
+    /// returned as both `data` and `meta` of the document.
+    pub async fn send_meta_document<T: DeserializeOwned, K: ApiEndpoint>(
+        base: K,
+    ) -> Result<ApiResponse<T>, String> {
+        let request = endpoint_request(&base);
+        let trace = Trace::request(&request);
+        let client = base.get_client();
+        let deadline = Deadline::start(&client.get_timeouts());
+        let response = interruptible(client.send(request), deadline, None).await;
+        trace.response(&response);
+        let result = response.and_then(|response| meta_document(response.parse()?));
+        trace.finish(&result, 1);
+        result
+    }
+}
+
+/// The pages of one logical request, which share its deadline and cancellation.
+struct Pages<'a> {
+    client: &'a UpClient,
+    token: &'a Token,
+    trace: &'a Trace,
+    deadline: Option<Deadline>,
+    cancel: Option<&'a CancelToken>,
+}
+
+impl Pages<'_> {
+    async fn fetch<T: DeserializeOwned>(
+        &self,
+        request: HttpRequest,
+    ) -> Result<ApiResponse<Vec<T>>, Interrupted<T>> {
+        let interrupted = |error| Interrupted {
+            items: Vec::new(),
+            error,
         };
+        let mut rtn = self.fetch_page(request, 1).await.map_err(interrupted)?;
+        let mut pages = 1;
+        while let Some(next) = rtn.next_link() {
+            pages += 1;
+            let request = HttpRequest::get(&next, self.token);
+            match self.fetch_page(request, pages).await {
+                Ok(page) => rtn.append_page(page),
+                Err(error) => {
+                    return Err(Interrupted {
+                        items: rtn.data,
+                        error,
+                    })
+                }
+            }
+        }
+        self.trace.pages(pages);
+        Ok(rtn)
+    }
+
+    async fn fetch_page<T: DeserializeOwned>(
+        &self,
+        request: HttpRequest,
+        page: usize,
+    ) -> Result<ApiResponse<Vec<T>>, String> {
+        let page_trace = self.trace.page(&request, page);
+        let response = interruptible(self.client.send(request), self.deadline, self.cancel).await;
+        self.trace.response(&response);
+        page_trace.response(&response);
+        let result: Result<ApiResponse<Vec<T>>, String> =
+            response.and_then(|response| response.parse());
+        page_trace.finish(&result, result.as_ref().map_or(0, |page| page.data.len()));
+        result
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::models;
+    use crate::request_sender::ApiResponse;
     use std::fs;
 
     #[tokio::test]
     async fn response_has_next() {
         let json: String = fs::read_to_string("tests/example_json/list_accounts.json").unwrap();
-        let des: models::ApiResponse<Vec<models::Account>> = serde_json::from_str(&*json).unwrap();
-        assert_eq!(models::ApiResponse::has_next(&des), true);
+        let des: ApiResponse<Vec<models::Account>> = serde_json::from_str(&json).unwrap();
+        assert!(des.next_link().is_some());
     }
 }
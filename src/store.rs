@@ -0,0 +1,451 @@
+use crate::models::{Account, Category, Tag, Transaction, TransactionStatus};
+use chrono::{DateTime, FixedOffset};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+/// Local storage for the resources fetched from the API and the high-water marks of
+/// `sync::Mirror`.
+///
+/// Upserts replace stored resources with the same id. See `Mirror::load` and `Mirror::save` for
+/// keeping a store in sync.
+pub trait Store {
+    fn upsert_accounts(&mut self, accounts: &[Account]) -> Result<(), String>;
+    fn get_account(&self, account_id: &str) -> Result<Option<Account>, String>;
+    fn get_accounts(&self) -> Result<Vec<Account>, String>;
+
+    fn upsert_categories(&mut self, categories: &[Category]) -> Result<(), String>;
+    fn get_category(&self, category_id: &str) -> Result<Option<Category>, String>;
+    fn get_categories(&self) -> Result<Vec<Category>, String>;
+
+    fn upsert_tags(&mut self, tags: &[Tag]) -> Result<(), String>;
+    fn get_tags(&self) -> Result<Vec<Tag>, String>;
+
+    fn upsert_transactions(&mut self, transactions: &[Transaction]) -> Result<(), String>;
+    fn remove_transactions(&mut self, transaction_ids: &[String]) -> Result<(), String>;
+    fn get_transaction(&self, transaction_id: &str) -> Result<Option<Transaction>, String>;
+    /// The transactions matching `query`, newest first.
+    fn query_transactions(&self, query: &TransactionQuery) -> Result<Vec<Transaction>, String>;
+
+    fn set_cursor(
+        &mut self,
+        account_id: &str,
+        high_water_mark: DateTime<FixedOffset>,
+    ) -> Result<(), String>;
+    /// The high-water mark of each account.
+    fn get_cursors(&self) -> Result<HashMap<String, DateTime<FixedOffset>>, String>;
+}
+
+/// Filters for `Store::query_transactions`. Like the API, `since` is inclusive, `until` is
+/// exclusive and `category` also matches transactions in its child categories.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransactionQuery {
+    pub account_id: Option<String>,
+    pub status: Option<TransactionStatus>,
+    pub since: Option<DateTime<FixedOffset>>,
+    pub until: Option<DateTime<FixedOffset>>,
+    pub category_id: Option<String>,
+    pub tag: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl TransactionQuery {
+    pub fn new() -> TransactionQuery {
+        TransactionQuery::default()
+    }
+
+    pub fn account(mut self, account_id: &str) -> TransactionQuery {
+        self.account_id = Some(account_id.to_string());
+        self
+    }
+
+    pub fn status(mut self, status: TransactionStatus) -> TransactionQuery {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn since(mut self, date_time: DateTime<FixedOffset>) -> TransactionQuery {
+        self.since = Some(date_time);
+        self
+    }
+
+    pub fn until(mut self, date_time: DateTime<FixedOffset>) -> TransactionQuery {
+        self.until = Some(date_time);
+        self
+    }
+
+    pub fn category(mut self, category_id: &str) -> TransactionQuery {
+        self.category_id = Some(category_id.to_string());
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> TransactionQuery {
+        self.tag = Some(tag.to_string());
+        self
+    }
+
+    /// Returns at most `limit` transactions, the newest ones.
+    pub fn limit(mut self, limit: usize) -> TransactionQuery {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Whether `transaction` passes the filters. `limit` is not taken into account.
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        let created_at = transaction.attributes.created_at;
+        self.account_id
+            .as_ref()
+            .is_none_or(|id| transaction.account_id() == id)
+            && self
+                .status
+                .is_none_or(|status| transaction.attributes.status == status)
+            && self.since.is_none_or(|since| created_at >= since)
+            && self.until.is_none_or(|until| created_at < until)
+            && self.category_id.as_ref().is_none_or(|id| {
+                transaction.category_id() == Some(id)
+                    || transaction.parent_category_id() == Some(id)
+            })
+            && self
+                .tag
+                .as_ref()
+                .is_none_or(|tag| transaction.tag_ids().contains(&tag))
+    }
+}
+
+/// A high-water mark, as stored in `cursors.jsonl`.
+#[derive(Serialize, Deserialize)]
+struct Cursor {
+    #[serde(rename = "accountId")]
+    account_id: String,
+    #[serde(rename = "highWaterMark")]
+    high_water_mark: DateTime<FixedOffset>,
+}
+
+/// A store that keeps each kind of resource in a file of one JSON document per line, e.g.
+/// `transactions.jsonl`, in a directory.
+///
+/// Everything is read into memory when the store is opened, and a file is rewritten whenever its
+/// contents change.
+pub struct JsonLinesStore {
+    dir: PathBuf,
+    accounts: BTreeMap<String, Account>,
+    categories: BTreeMap<String, Category>,
+    tags: BTreeMap<String, Tag>,
+    transactions: BTreeMap<String, Transaction>,
+    cursors: BTreeMap<String, DateTime<FixedOffset>>,
+}
+
+impl JsonLinesStore {
+    /// Opens the store in `dir`, creating the directory if needed.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<JsonLinesStore, String> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        let cursors: Vec<Cursor> = read_lines(&dir.join("cursors.jsonl"))?;
+        Ok(JsonLinesStore {
+            accounts: by_id(read_lines(&dir.join("accounts.jsonl"))?, |a: &Account| {
+                &a.id
+            }),
+            categories: by_id(
+                read_lines(&dir.join("categories.jsonl"))?,
+                |c: &Category| &c.id,
+            ),
+            tags: by_id(read_lines(&dir.join("tags.jsonl"))?, |t: &Tag| &t.id),
+            transactions: by_id(
+                read_lines(&dir.join("transactions.jsonl"))?,
+                |t: &Transaction| &t.id,
+            ),
+            cursors: cursors
+                .into_iter()
+                .map(|cursor| (cursor.account_id, cursor.high_water_mark))
+                .collect(),
+            dir,
+        })
+    }
+}
+
+fn by_id<T, F: Fn(&T) -> &String>(values: Vec<T>, id: F) -> BTreeMap<String, T> {
+    values
+        .into_iter()
+        .map(|value| (id(&value).to_string(), value))
+        .collect()
+}
+
+fn read_lines<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e))
+        })
+        .collect()
+}
+
+/// Writes to a temporary file first, so a crash never leaves a half-written file behind.
+fn write_lines<'a, T: Serialize + 'a, I: Iterator<Item = &'a T>>(
+    path: &Path,
+    values: I,
+) -> Result<(), String> {
+    let mut contents = String::new();
+    for value in values {
+        contents.push_str(&serde_json::to_string(value).map_err(|e| e.to_string())?);
+        contents.push('\n');
+    }
+    let temporary = path.with_extension("jsonl.tmp");
+    fs::write(&temporary, contents).map_err(|e| format!("{}: {}", temporary.display(), e))?;
+    fs::rename(&temporary, path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+impl Store for JsonLinesStore {
+    fn upsert_accounts(&mut self, accounts: &[Account]) -> Result<(), String> {
+        for account in accounts {
+            self.accounts
+                .insert(account.id.to_string(), account.clone());
+        }
+        write_lines(&self.dir.join("accounts.jsonl"), self.accounts.values())
+    }
+
+    fn get_account(&self, account_id: &str) -> Result<Option<Account>, String> {
+        Ok(self.accounts.get(account_id).cloned())
+    }
+
+    fn get_accounts(&self) -> Result<Vec<Account>, String> {
+        Ok(self.accounts.values().cloned().collect())
+    }
+
+    fn upsert_categories(&mut self, categories: &[Category]) -> Result<(), String> {
+        for category in categories {
+            self.categories
+                .insert(category.id.to_string(), category.clone());
+        }
+        write_lines(&self.dir.join("categories.jsonl"), self.categories.values())
+    }
+
+    fn get_category(&self, category_id: &str) -> Result<Option<Category>, String> {
+        Ok(self.categories.get(category_id).cloned())
+    }
+
+    fn get_categories(&self) -> Result<Vec<Category>, String> {
+        Ok(self.categories.values().cloned().collect())
+    }
+
+    fn upsert_tags(&mut self, tags: &[Tag]) -> Result<(), String> {
+        for tag in tags {
+            self.tags.insert(tag.id.to_string(), tag.clone());
+        }
+        write_lines(&self.dir.join("tags.jsonl"), self.tags.values())
+    }
+
+    fn get_tags(&self) -> Result<Vec<Tag>, String> {
+        Ok(self.tags.values().cloned().collect())
+    }
+
+    fn upsert_transactions(&mut self, transactions: &[Transaction]) -> Result<(), String> {
+        for transaction in transactions {
+            self.transactions
+                .insert(transaction.id.to_string(), transaction.clone());
+        }
+        write_lines(
+            &self.dir.join("transactions.jsonl"),
+            self.transactions.values(),
+        )
+    }
+
+    fn remove_transactions(&mut self, transaction_ids: &[String]) -> Result<(), String> {
+        for transaction_id in transaction_ids {
+            self.transactions.remove(transaction_id);
+        }
+        write_lines(
+            &self.dir.join("transactions.jsonl"),
+            self.transactions.values(),
+        )
+    }
+
+    fn get_transaction(&self, transaction_id: &str) -> Result<Option<Transaction>, String> {
+        Ok(self.transactions.get(transaction_id).cloned())
+    }
+
+    fn query_transactions(&self, query: &TransactionQuery) -> Result<Vec<Transaction>, String> {
+        let mut transactions: Vec<Transaction> = self
+            .transactions
+            .values()
+            .filter(|transaction| query.matches(transaction))
+            .cloned()
+            .collect();
+        transactions.sort_by(|a, b| b.cmp(a));
+        if let Some(limit) = query.limit {
+            transactions.truncate(limit);
+        }
+        Ok(transactions)
+    }
+
+    fn set_cursor(
+        &mut self,
+        account_id: &str,
+        high_water_mark: DateTime<FixedOffset>,
+    ) -> Result<(), String> {
+        self.cursors.insert(account_id.to_string(), high_water_mark);
+        let cursors: Vec<Cursor> = self
+            .cursors
+            .iter()
+            .map(|(account_id, high_water_mark)| Cursor {
+                account_id: account_id.to_string(),
+                high_water_mark: *high_water_mark,
+            })
+            .collect();
+        write_lines(&self.dir.join("cursors.jsonl"), cursors.iter())
+    }
+
+    fn get_cursors(&self) -> Result<HashMap<String, DateTime<FixedOffset>>, String> {
+        Ok(self
+            .cursors
+            .iter()
+            .map(|(account_id, mark)| (account_id.to_string(), *mark))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::AccountType;
+    use crate::request_sender::ApiRequest;
+    use crate::store::*;
+    use crate::sync::Mirror;
+    use crate::testing::{self, at, MockUpApi};
+
+    fn mock() -> MockUpApi {
+        let held = testing::held_transaction(
+            "t2",
+            "spending",
+            "Coffee",
+            -450,
+            at("2022-10-02T08:00:00+11:00"),
+        );
+        MockUpApi::new()
+            .account(testing::account(
+                "spending",
+                "Spending",
+                AccountType::Transactional,
+                0,
+            ))
+            .account(testing::account("saver", "Saver", AccountType::Saver, 0))
+            .category(testing::category("good-life", "Good Life", None))
+            .category(testing::category("booze", "Booze", Some("good-life")))
+            .transaction(testing::transaction(
+                "t1",
+                "spending",
+                "Bottle shop",
+                -3000,
+                at("2022-10-01T18:00:00+10:00"),
+            ))
+            .transaction(held)
+            .transaction(testing::transaction(
+                "t3",
+                "saver",
+                "Interest",
+                12,
+                at("2022-10-03T00:00:00+11:00"),
+            ))
+    }
+
+    /// Syncs the mock into `store`, then checks what a fresh mirror reads back from it.
+    pub(crate) async fn exercise(store: &mut dyn Store) {
+        let mock = mock();
+        let client = mock.client();
+        client
+            .categorize_transaction("t1".to_string(), Some("booze".to_string()))
+            .send()
+            .await
+            .unwrap();
+        client
+            .add_tag_to_transaction("t2".to_string(), vec!["Coffee".to_string()])
+            .send()
+            .await
+            .unwrap();
+        store
+            .upsert_categories(&client.list_categories().send().await.unwrap())
+            .unwrap();
+        store
+            .upsert_tags(&client.list_tags().send().await.unwrap())
+            .unwrap();
+
+        let mut mirror = Mirror::new(&client);
+        let changes = mirror.sync().await.unwrap();
+        mirror.save(store, &changes).unwrap();
+        let mock = mock.remove_transaction("t2");
+        let changes = mirror.sync().await.unwrap();
+        mirror.save(store, &changes).unwrap();
+
+        assert_eq!(store.get_accounts().unwrap().len(), 2);
+        assert_eq!(store.get_categories().unwrap().len(), 2);
+        assert_eq!(
+            store.get_category("booze").unwrap().unwrap().parent_id(),
+            Some(&"good-life".to_string())
+        );
+        assert_eq!(store.get_tags().unwrap()[0].id, "Coffee");
+        assert!(store.get_transaction("t2").unwrap().is_none());
+        assert_eq!(
+            store.get_transaction("t1").unwrap().unwrap().category_id(),
+            Some(&"booze".to_string())
+        );
+
+        let ids = |query: TransactionQuery| -> Vec<String> {
+            let transactions = store.query_transactions(&query).unwrap();
+            transactions.into_iter().map(|t| t.id).collect()
+        };
+        assert_eq!(ids(TransactionQuery::new()), vec!["t3", "t1"]);
+        assert_eq!(ids(TransactionQuery::new().limit(1)), vec!["t3"]);
+        assert_eq!(ids(TransactionQuery::new().account("spending")), vec!["t1"]);
+        assert_eq!(
+            ids(TransactionQuery::new().category("good-life")),
+            vec!["t1"]
+        );
+        assert_eq!(
+            ids(TransactionQuery::new()
+                .since(at("2022-10-01T18:00:00+10:00"))
+                .until(at("2022-10-03T00:00:00+11:00"))),
+            vec!["t1"]
+        );
+
+        let reloaded = Mirror::load(&client, store).unwrap();
+        let state = reloaded.get_state();
+        assert_eq!(state.transactions.len(), 2);
+        assert_eq!(
+            state.high_water_marks["saver"],
+            at("2022-10-03T00:00:00+11:00")
+        );
+        assert_eq!(mock.get_transactions().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn json_lines_store_round_trips() {
+        let dir = std::env::temp_dir().join(format!("uprs-store-{}", std::process::id()));
+        let mut store = JsonLinesStore::open(&dir).unwrap();
+        exercise(&mut store).await;
+
+        let reopened = JsonLinesStore::open(&dir).unwrap();
+        assert_eq!(
+            reopened
+                .get_transaction("t1")
+                .unwrap()
+                .unwrap()
+                .attributes
+                .description,
+            "Bottle shop"
+        );
+        assert_eq!(reopened.get_cursors().unwrap().len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,404 @@
+use crate::models::{api_value, Account, Category, Tag, Transaction};
+use crate::store::{Store, TransactionQuery};
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS accounts (
+    id TEXT PRIMARY KEY,
+    display_name TEXT NOT NULL,
+    account_type TEXT NOT NULL,
+    ownership_type TEXT NOT NULL,
+    balance INTEGER NOT NULL,
+    currency_code TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    json TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS categories (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    parent_id TEXT,
+    json TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tags (
+    id TEXT PRIMARY KEY,
+    json TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS transactions (
+    id TEXT PRIMARY KEY,
+    account_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    description TEXT NOT NULL,
+    raw_text TEXT,
+    message TEXT,
+    amount INTEGER NOT NULL,
+    currency_code TEXT NOT NULL,
+    foreign_amount INTEGER,
+    foreign_currency_code TEXT,
+    category_id TEXT,
+    parent_category_id TEXT,
+    transfer_account_id TEXT,
+    created_at TEXT NOT NULL,
+    settled_at TEXT,
+    json TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS transactions_by_account ON transactions (account_id, created_at);
+CREATE INDEX IF NOT EXISTS transactions_by_created_at ON transactions (created_at);
+CREATE TABLE IF NOT EXISTS transaction_tags (
+    transaction_id TEXT NOT NULL,
+    tag_id TEXT NOT NULL,
+    PRIMARY KEY (transaction_id, tag_id)
+);
+CREATE TABLE IF NOT EXISTS sync_cursors (
+    account_id TEXT PRIMARY KEY,
+    high_water_mark TEXT NOT NULL
+);
+";
+
+/// A store in an SQLite database, for querying the synced data with SQL.
+///
+/// Each resource is kept as JSON in the `json` column of its table, next to columns for its
+/// attributes and relationships. Amounts are in base units (e.g. cents) and date-times are UTC
+/// RFC 3339 strings, so they sort and compare correctly as text. The tags of transactions are in
+/// `transaction_tags`.
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it and its tables if needed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteStore, String> {
+        let connection = Connection::open(path.as_ref())
+            .map_err(|e| format!("{}: {}", path.as_ref().display(), e))?;
+        SqliteStore::with_connection(connection)
+    }
+
+    pub fn open_in_memory() -> Result<SqliteStore, String> {
+        SqliteStore::with_connection(Connection::open_in_memory().map_err(|e| e.to_string())?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<SqliteStore, String> {
+        connection
+            .execute_batch(SCHEMA)
+            .map_err(|e| e.to_string())?;
+        Ok(SqliteStore { connection })
+    }
+
+    /// The underlying connection, e.g. for running your own queries.
+    pub fn get_connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    fn get_one<T: DeserializeOwned>(&self, table: &str, id: &str) -> Result<Option<T>, String> {
+        let json: Option<String> = self
+            .connection
+            .query_row(
+                &format!("SELECT json FROM {} WHERE id = ?1", table),
+                [id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        json.map(|json| from_json(&json)).transpose()
+    }
+
+    fn get_all<T: DeserializeOwned>(&self, table: &str) -> Result<Vec<T>, String> {
+        self.query(
+            &format!("SELECT json FROM {} ORDER BY id", table),
+            Vec::new(),
+        )
+    }
+
+    fn query<T: DeserializeOwned>(&self, sql: &str, values: Vec<String>) -> Result<Vec<T>, String> {
+        let mut statement = self.connection.prepare(sql).map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params_from_iter(values), |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        rows.map(|json| from_json(&json.map_err(|e| e.to_string())?))
+            .collect()
+    }
+}
+
+fn from_json<T: DeserializeOwned>(json: &str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|e| e.to_string())
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+fn utc(date_time: &DateTime<FixedOffset>) -> String {
+    date_time
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn base_units(value: i128) -> Result<i64, String> {
+    i64::try_from(value).map_err(|_| format!("{} does not fit in an SQLite integer.", value))
+}
+
+impl Store for SqliteStore {
+    fn upsert_accounts(&mut self, accounts: &[Account]) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        for account in accounts {
+            let attributes = &account.attributes;
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO accounts (id, display_name, account_type, \
+                     ownership_type, balance, currency_code, created_at, json) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                    params![
+                        account.id,
+                        attributes.display_name,
+                        api_value(&attributes.account_type),
+                        api_value(&attributes.ownership_type),
+                        base_units(attributes.balance.value_in_base_units)?,
+                        attributes.balance.currency_code,
+                        utc(&attributes.created_at),
+                        to_json(account)?,
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    fn get_account(&self, account_id: &str) -> Result<Option<Account>, String> {
+        self.get_one("accounts", account_id)
+    }
+
+    fn get_accounts(&self) -> Result<Vec<Account>, String> {
+        self.get_all("accounts")
+    }
+
+    fn upsert_categories(&mut self, categories: &[Category]) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        for category in categories {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO categories (id, name, parent_id, json) \
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        category.id,
+                        category.attributes.name,
+                        category.parent_id(),
+                        to_json(category)?,
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    fn get_category(&self, category_id: &str) -> Result<Option<Category>, String> {
+        self.get_one("categories", category_id)
+    }
+
+    fn get_categories(&self) -> Result<Vec<Category>, String> {
+        self.get_all("categories")
+    }
+
+    fn upsert_tags(&mut self, tags: &[Tag]) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        for tag in tags {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO tags (id, json) VALUES (?1, ?2)",
+                    params![tag.id, to_json(tag)?],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    fn get_tags(&self) -> Result<Vec<Tag>, String> {
+        self.get_all("tags")
+    }
+
+    fn upsert_transactions(&mut self, transactions: &[Transaction]) -> Result<(), String> {
+        let sql_transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        for transaction in transactions {
+            let attributes = &transaction.attributes;
+            let foreign_amount = match &attributes.foreign_amount {
+                None => None,
+                Some(money) => Some(base_units(money.value_in_base_units)?),
+            };
+            sql_transaction
+                .execute(
+                    "INSERT OR REPLACE INTO transactions (id, account_id, status, description, \
+                     raw_text, message, amount, currency_code, foreign_amount, \
+                     foreign_currency_code, category_id, parent_category_id, \
+                     transfer_account_id, created_at, settled_at, json) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, \
+                     ?16)",
+                    params![
+                        transaction.id,
+                        transaction.account_id(),
+                        api_value(&attributes.status),
+                        attributes.description,
+                        attributes.raw_text,
+                        attributes.message,
+                        base_units(attributes.amount.value_in_base_units)?,
+                        attributes.amount.currency_code,
+                        foreign_amount,
+                        attributes
+                            .foreign_amount
+                            .as_ref()
+                            .map(|money| &money.currency_code),
+                        transaction.category_id(),
+                        transaction.parent_category_id(),
+                        transaction.transfer_account_id(),
+                        utc(&attributes.created_at),
+                        attributes.settled_at.as_ref().map(utc),
+                        to_json(transaction)?,
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            sql_transaction
+                .execute(
+                    "DELETE FROM transaction_tags WHERE transaction_id = ?1",
+                    [&transaction.id],
+                )
+                .map_err(|e| e.to_string())?;
+            for tag_id in transaction.tag_ids() {
+                sql_transaction
+                    .execute(
+                        "INSERT INTO transaction_tags (transaction_id, tag_id) VALUES (?1, ?2)",
+                        [&transaction.id, tag_id],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        sql_transaction.commit().map_err(|e| e.to_string())
+    }
+
+    fn remove_transactions(&mut self, transaction_ids: &[String]) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        for transaction_id in transaction_ids {
+            for sql in [
+                "DELETE FROM transactions WHERE id = ?1",
+                "DELETE FROM transaction_tags WHERE transaction_id = ?1",
+            ] {
+                transaction
+                    .execute(sql, [transaction_id])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    fn get_transaction(&self, transaction_id: &str) -> Result<Option<Transaction>, String> {
+        self.get_one("transactions", transaction_id)
+    }
+
+    fn query_transactions(&self, query: &TransactionQuery) -> Result<Vec<Transaction>, String> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        let mut condition = |sql: &str, value: String| {
+            conditions.push(sql.replace('?', &format!("?{}", values.len() + 1)));
+            values.push(value);
+        };
+        if let Some(account_id) = &query.account_id {
+            condition("account_id = ?", account_id.to_string());
+        }
+        if let Some(status) = query.status {
+            condition("status = ?", api_value(&status));
+        }
+        if let Some(since) = &query.since {
+            condition("created_at >= ?", utc(since));
+        }
+        if let Some(until) = &query.until {
+            condition("created_at < ?", utc(until));
+        }
+        if let Some(category_id) = &query.category_id {
+            condition(
+                "? IN (category_id, parent_category_id)",
+                category_id.to_string(),
+            );
+        }
+        if let Some(tag) = &query.tag {
+            condition(
+                "id IN (SELECT transaction_id FROM transaction_tags WHERE tag_id = ?)",
+                tag.to_string(),
+            );
+        }
+        let mut sql = "SELECT json FROM transactions".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+        sql.push_str(" ORDER BY created_at DESC, id DESC");
+        if let Some(limit) = query.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        self.query(&sql, values)
+    }
+
+    fn set_cursor(
+        &mut self,
+        account_id: &str,
+        high_water_mark: DateTime<FixedOffset>,
+    ) -> Result<(), String> {
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO sync_cursors (account_id, high_water_mark) \
+                 VALUES (?1, ?2)",
+                params![account_id, utc(&high_water_mark)],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    fn get_cursors(&self) -> Result<HashMap<String, DateTime<FixedOffset>>, String> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT account_id, high_water_mark FROM sync_cursors")
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.map(|row| {
+            let (account_id, mark) = row.map_err(|e| e.to_string())?;
+            let mark = DateTime::parse_from_rfc3339(&mark).map_err(|e| e.to_string())?;
+            Ok((account_id, mark))
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::store::sqlite::*;
+    use crate::store::tests::exercise;
+
+    #[tokio::test]
+    async fn sqlite_store_round_trips() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        exercise(&mut store).await;
+
+        let spent: i64 = store
+            .get_connection()
+            .query_row(
+                "SELECT SUM(amount) FROM transactions WHERE status = 'SETTLED'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(spent, -3000 + 12);
+
+        let cursors: Vec<String> = store
+            .get_connection()
+            .prepare("SELECT high_water_mark FROM sync_cursors")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert!(!cursors.is_empty());
+        assert!(cursors.iter().all(|cursor| cursor.ends_with('Z')));
+    }
+}
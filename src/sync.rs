@@ -0,0 +1,377 @@
+use crate::api_endpoints::{ListTransactions, BASE_URL};
+use crate::client::UpClient;
+use crate::models::{Account, Transaction, TransactionStatus};
+use crate::request_sender::ApiRequest;
+use crate::store::{Store, TransactionQuery};
+use chrono::{DateTime, Duration, FixedOffset};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// How far before the high-water mark of an account a sync starts looking, unless told otherwise.
+const DEFAULT_OVERLAP_DAYS: i64 = 7;
+
+/// The local copy of the accounts and transactions kept by a `Mirror`. It can be serialized to
+/// carry it between runs.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SyncState {
+    pub accounts: HashMap<String, Account>,
+    pub transactions: HashMap<String, Transaction>,
+    /// The latest `createdAt` seen for each account.
+    pub high_water_marks: HashMap<String, DateTime<FixedOffset>>,
+}
+
+/// A change to a transaction found by `Mirror::sync`. A transaction can have several changes in
+/// one sync, e.g. `Settled` and `AmountChanged` when a hold settles for a different amount.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    New(Transaction),
+    /// A `HELD` transaction is now `SETTLED`.
+    Settled {
+        before: Transaction,
+        after: Transaction,
+    },
+    AmountChanged {
+        before: Transaction,
+        after: Transaction,
+    },
+    CategoryChanged {
+        before: Transaction,
+        after: Transaction,
+    },
+    TagsChanged {
+        before: Transaction,
+        after: Transaction,
+    },
+    /// A transaction that is no longer returned by the API, e.g. a hold that was released.
+    Disappeared(Transaction),
+}
+
+impl Change {
+    /// The transaction as it is now, or as it was last seen for `Disappeared`.
+    pub fn transaction(&self) -> &Transaction {
+        match self {
+            Change::New(transaction) | Change::Disappeared(transaction) => transaction,
+            Change::Settled { after, .. }
+            | Change::AmountChanged { after, .. }
+            | Change::CategoryChanged { after, .. }
+            | Change::TagsChanged { after, .. } => after,
+        }
+    }
+}
+
+/// Keeps a local mirror of every account and transaction up to date.
+///
+/// The first sync fetches everything. Later syncs only fetch the transactions of each account
+/// created since `overlap` before its high-water mark, and since the oldest transaction of the
+/// account that is still held, so that late transactions and settled holds are picked up.
+/// Transactions in that window that are no longer returned are reported as `Disappeared`.
+pub struct Mirror {
+    client: UpClient,
+    overlap: Duration,
+    state: SyncState,
+}
+
+impl Mirror {
+    pub fn new(client: &UpClient) -> Mirror {
+        Mirror::with_state(client, SyncState::default())
+    }
+
+    /// Continues from the state of an earlier run.
+    pub fn with_state(client: &UpClient, state: SyncState) -> Mirror {
+        Mirror {
+            client: client.clone(),
+            overlap: Duration::days(DEFAULT_OVERLAP_DAYS),
+            state,
+        }
+    }
+
+    /// How far before the high-water mark each sync starts looking. Defaults to 7 days.
+    pub fn overlap(mut self, overlap: Duration) -> Mirror {
+        if overlap >= Duration::zero() {
+            self.overlap = overlap;
+        } else {
+            eprintln!("Overlap can not be negative.");
+        }
+        self
+    }
+
+    /// Continues from the accounts, transactions and high-water marks saved in `store`.
+    pub fn load(client: &UpClient, store: &dyn Store) -> Result<Mirror, String> {
+        let state = SyncState {
+            accounts: store
+                .get_accounts()?
+                .into_iter()
+                .map(|account| (account.id.to_string(), account))
+                .collect(),
+            transactions: store
+                .query_transactions(&TransactionQuery::new())?
+                .into_iter()
+                .map(|transaction| (transaction.id.to_string(), transaction))
+                .collect(),
+            high_water_marks: store.get_cursors()?,
+        };
+        Ok(Mirror::with_state(client, state))
+    }
+
+    /// Writes the accounts, the `changes` returned by the last sync and the high-water marks to
+    /// `store`.
+    pub fn save(&self, store: &mut dyn Store, changes: &[Change]) -> Result<(), String> {
+        let accounts: Vec<Account> = self.state.accounts.values().cloned().collect();
+        store.upsert_accounts(&accounts)?;
+        let mut changed = Vec::new();
+        let mut disappeared = Vec::new();
+        for change in changes {
+            let id = &change.transaction().id;
+            match self.state.transactions.get(id) {
+                Some(transaction) => changed.push(transaction.clone()),
+                None => disappeared.push(id.to_string()),
+            }
+        }
+        store.upsert_transactions(&changed)?;
+        store.remove_transactions(&disappeared)?;
+        for (account_id, high_water_mark) in &self.state.high_water_marks {
+            store.set_cursor(account_id, *high_water_mark)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_state(&self) -> &SyncState {
+        &self.state
+    }
+
+    pub fn into_state(self) -> SyncState {
+        self.state
+    }
+
+    /// Brings the mirror up to date and returns the changes since the last sync, oldest
+    /// transaction first. The mirror is left as it was if any request fails.
+    pub async fn sync(&mut self) -> Result<Vec<Change>, String> {
+        let accounts = self.client.list_accounts().send().await?;
+        let mut fetched = Vec::new();
+        for account in &accounts {
+            let since = self.window_start(&account.id);
+            let url = format!("{}/accounts/{}/transactions", BASE_URL, account.id);
+            let mut request = ListTransactions::from_url(&self.client, url);
+            if let Some(since) = since {
+                request = request.since(since);
+            }
+            fetched.push((account.id.to_string(), since, request.send().await?));
+        }
+
+        self.state.accounts = accounts
+            .into_iter()
+            .map(|account| (account.id.to_string(), account))
+            .collect();
+        let mut changes = Vec::new();
+        for (account_id, since, transactions) in fetched {
+            changes.append(&mut self.apply(&account_id, since, transactions));
+        }
+        Ok(changes)
+    }
+
+    /// Where the next fetch for an account starts. `None` fetches everything.
+    fn window_start(&self, account_id: &str) -> Option<DateTime<FixedOffset>> {
+        let start = *self.state.high_water_marks.get(account_id)? - self.overlap;
+        let oldest_held = self
+            .state
+            .transactions
+            .values()
+            .filter(|transaction| {
+                transaction.account_id() == account_id
+                    && transaction.attributes.status == TransactionStatus::Held
+            })
+            .map(|transaction| transaction.attributes.created_at)
+            .min();
+        Some(oldest_held.map_or(start, |held| held.min(start)))
+    }
+
+    fn apply(
+        &mut self,
+        account_id: &str,
+        since: Option<DateTime<FixedOffset>>,
+        transactions: Vec<Transaction>,
+    ) -> Vec<Change> {
+        let returned: HashSet<&String> = transactions.iter().map(|t| &t.id).collect();
+        let mut gone: Vec<&Transaction> = self
+            .state
+            .transactions
+            .values()
+            .filter(|transaction| {
+                transaction.account_id() == account_id
+                    && since.is_none_or(|since| transaction.attributes.created_at >= since)
+                    && !returned.contains(&transaction.id)
+            })
+            .collect();
+        gone.sort();
+        let gone: Vec<String> = gone.iter().map(|t| t.id.to_string()).collect();
+
+        let mut changes = Vec::new();
+        let mut transactions = transactions;
+        transactions.sort();
+        for transaction in transactions {
+            let created_at = transaction.attributes.created_at;
+            let mark = self
+                .state
+                .high_water_marks
+                .entry(account_id.to_string())
+                .or_insert(created_at);
+            *mark = created_at.max(*mark);
+            let id = transaction.id.to_string();
+            match self.state.transactions.insert(id, transaction.clone()) {
+                None => changes.push(Change::New(transaction)),
+                Some(before) => changes.append(&mut compare(before, transaction)),
+            }
+        }
+        for id in gone {
+            if let Some(transaction) = self.state.transactions.remove(&id) {
+                changes.push(Change::Disappeared(transaction));
+            }
+        }
+        changes
+    }
+}
+
+fn compare(before: Transaction, after: Transaction) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if before.attributes.status == TransactionStatus::Held
+        && after.attributes.status == TransactionStatus::Settled
+    {
+        changes.push(Change::Settled {
+            before: before.clone(),
+            after: after.clone(),
+        });
+    }
+    if before.attributes.amount != after.attributes.amount {
+        changes.push(Change::AmountChanged {
+            before: before.clone(),
+            after: after.clone(),
+        });
+    }
+    if before.category_id() != after.category_id() {
+        changes.push(Change::CategoryChanged {
+            before: before.clone(),
+            after: after.clone(),
+        });
+    }
+    let mut tags_before = before.tag_ids();
+    let mut tags_after = after.tag_ids();
+    tags_before.sort();
+    tags_after.sort();
+    if tags_before != tags_after {
+        changes.push(Change::TagsChanged { before, after });
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{AccountType, TransactionStatus};
+    use crate::request_sender::ApiRequest;
+    use crate::sync::*;
+    use crate::testing::{self, at, held_transaction, MockUpApi};
+
+    fn kinds(changes: &[Change]) -> Vec<String> {
+        changes
+            .iter()
+            .map(|change| {
+                let kind = format!("{:?}", change);
+                let kind = kind.split(['(', ' ']).next().unwrap().to_string();
+                format!("{} {}", kind, change.transaction().id)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn reports_changes_between_syncs() {
+        let mock = MockUpApi::new()
+            .account(testing::account(
+                "spending",
+                "Spending",
+                AccountType::Transactional,
+                0,
+            ))
+            .transaction(testing::transaction(
+                "old",
+                "spending",
+                "Old",
+                -100,
+                at("2022-09-01T09:00:00+10:00"),
+            ))
+            .transaction(held_transaction(
+                "coffee",
+                "spending",
+                "coffee",
+                -450,
+                at("2022-09-20T09:00:00+10:00"),
+            ))
+            .transaction(held_transaction(
+                "hotel",
+                "spending",
+                "hotel",
+                -20000,
+                at("2022-09-25T09:00:00+10:00"),
+            ))
+            .transaction(held_transaction(
+                "fuel",
+                "spending",
+                "fuel",
+                -6000,
+                at("2022-09-30T09:00:00+10:00"),
+            ));
+        let client = mock.client();
+        let mut mirror = Mirror::new(&client).overlap(Duration::days(1));
+        assert_eq!(mirror.sync().await.unwrap().len(), 4);
+        assert_eq!(
+            mirror.get_state().high_water_marks["spending"],
+            at("2022-09-30T09:00:00+10:00")
+        );
+
+        let mut coffee = testing::transaction(
+            "coffee",
+            "spending",
+            "coffee",
+            -500,
+            at("2022-09-20T09:00:00+10:00"),
+        );
+        coffee.attributes.status = TransactionStatus::Settled;
+        let mock = mock
+            .transaction(coffee)
+            .remove_transaction("hotel")
+            .transaction(testing::transaction(
+                "lunch",
+                "spending",
+                "Lunch",
+                -1500,
+                at("2022-10-01T12:00:00+10:00"),
+            ));
+        client
+            .add_tag_to_transaction("fuel".to_string(), vec!["car".to_string()])
+            .send()
+            .await
+            .unwrap();
+
+        let changes = mirror.sync().await.unwrap();
+        assert_eq!(
+            kinds(&changes),
+            vec![
+                "Settled coffee",
+                "AmountChanged coffee",
+                "TagsChanged fuel",
+                "New lunch",
+                "Disappeared hotel",
+            ]
+        );
+        // The window starts at the oldest hold rather than a day before the high-water mark.
+        let request = mock.get_requests().pop().unwrap();
+        let since = request
+            .query
+            .iter()
+            .find(|(name, _)| name == "filter[since]");
+        assert_eq!(since.unwrap().1, "2022-09-20T09:00:00+10:00");
+
+        assert!(mirror.sync().await.unwrap().is_empty());
+        let state = mirror.into_state();
+        assert_eq!(state.transactions.len(), 4);
+        assert!(!state.transactions.contains_key("hotel"));
+    }
+}
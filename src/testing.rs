@@ -0,0 +1,1200 @@
+use crate::api_endpoints::BASE_URL;
+use crate::client::UpClient;
+use crate::models::*;
+use crate::transport::{HttpRequest, HttpResponse, HttpTransport, Method};
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const DEFAULT_PAGE_SIZE: usize = 10;
+
+/// An in-process fake of the Up API, used as the transport of an `UpClient`.
+///
+/// The mock is seeded with accounts, categories, tags and transactions and answers requests to the
+/// same URLs as the real API: listing and retrieving resources, `filter[...]` parameters, cursor
+/// pagination with `page[size]`, the endpoints that categorize and tag transactions, and the
+/// webhook endpoints, where a ping is logged as delivered. Clones share the same data, so a test
+/// can keep a handle to inspect what was written or sent.
+///
+/// ```
+/// use uprs::models::AccountType;
+/// use uprs::request_sender::ApiRequest;
+/// use uprs::testing::{self, MockUpApi};
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let mock = MockUpApi::new().account(testing::account(
+///     "1",
+///     "Spending",
+///     AccountType::Transactional,
+///     1050,
+/// ));
+/// let accounts = mock.client().list_accounts().send().await.unwrap();
+/// assert_eq!(accounts[0].attributes.balance.value, "10.50");
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct MockUpApi {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    api_key: Option<String>,
+    accounts: Vec<Account>,
+    categories: Vec<Category>,
+    tags: Vec<String>,
+    transactions: Vec<Transaction>,
+    webhooks: Vec<Webhook>,
+    webhook_logs: Vec<(String, WebhookDeliveryLog)>,
+    requests: Vec<HttpRequest>,
+}
+
+impl MockUpApi {
+    pub fn new() -> MockUpApi {
+        MockUpApi::default()
+    }
+
+    /// Only accepts requests made with `api_key`. By default any bearer token is accepted.
+    pub fn api_key(self, api_key: &String) -> MockUpApi {
+        self.lock().api_key = Some(api_key.to_string());
+        self
+    }
+
+    pub fn account(self, account: Account) -> MockUpApi {
+        self.lock().accounts.push(account);
+        self
+    }
+
+    pub fn category(self, category: Category) -> MockUpApi {
+        self.lock().categories.push(category);
+        self
+    }
+
+    pub fn tag(self, tag: &str) -> MockUpApi {
+        self.lock().add_tag(tag);
+        self
+    }
+
+    /// Adds a transaction. Its tags are added to the list of tags, and its parent category is set
+    /// from the seeded categories.
+    pub fn transaction(self, transaction: Transaction) -> MockUpApi {
+        self.lock().add_transaction(transaction);
+        self
+    }
+
+    /// Removes a transaction, e.g. to act out a hold that was released.
+    pub fn remove_transaction(self, transaction_id: &str) -> MockUpApi {
+        self.lock()
+            .transactions
+            .retain(|transaction| transaction.id != transaction_id);
+        self
+    }
+
+    /// A client that sends its requests to this mock.
+    pub fn client(&self) -> UpClient {
+        let api_key = match &self.lock().api_key {
+            None => "up:mock:token".to_string(),
+            Some(api_key) => api_key.to_string(),
+        };
+        UpClient::new(&api_key).transport(self.clone())
+    }
+
+    pub fn get_accounts(&self) -> Vec<Account> {
+        self.lock().accounts.to_vec()
+    }
+
+    pub fn get_categories(&self) -> Vec<Category> {
+        self.lock().categories.to_vec()
+    }
+
+    pub fn get_tags(&self) -> Vec<String> {
+        self.lock().tags.to_vec()
+    }
+
+    /// The transactions, newest first.
+    pub fn get_transactions(&self) -> Vec<Transaction> {
+        self.lock().transactions.to_vec()
+    }
+
+    pub fn get_transaction(&self, transaction_id: &str) -> Option<Transaction> {
+        self.lock()
+            .transactions
+            .iter()
+            .find(|transaction| transaction.id == transaction_id)
+            .cloned()
+    }
+
+    pub fn get_webhooks(&self) -> Vec<Webhook> {
+        self.lock().webhooks.to_vec()
+    }
+
+    /// Every request received so far, in order.
+    pub fn get_requests(&self) -> Vec<HttpRequest> {
+        self.lock().requests.to_vec()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl HttpTransport for MockUpApi {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let mut state = self.lock();
+        state.requests.push(request.clone());
+        Ok(state.handle(request).unwrap_or_else(|error| error))
+    }
+
+    #[cfg(feature = "blocking")]
+    fn send_blocking(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let mut state = self.lock();
+        state.requests.push(request.clone());
+        Ok(state.handle(request).unwrap_or_else(|error| error))
+    }
+}
+
+impl MockState {
+    fn add_tag(&mut self, tag: &str) {
+        if !self.tags.iter().any(|existing| existing == tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+
+    fn add_transaction(&mut self, mut transaction: Transaction) {
+        for tag in transaction.tag_ids() {
+            let tag = tag.to_string();
+            self.add_tag(&tag);
+        }
+        let category_id = transaction.category_id().map(|id| id.to_string());
+        self.set_category(&mut transaction, category_id);
+        self.transactions
+            .retain(|existing| existing.id != transaction.id);
+        let position = self
+            .transactions
+            .iter()
+            .position(|existing| existing < &transaction)
+            .unwrap_or(self.transactions.len());
+        self.transactions.insert(position, transaction);
+    }
+
+    fn set_category(&self, transaction: &mut Transaction, category_id: Option<String>) {
+        let parent_id = category_id.as_ref().and_then(|id| {
+            self.categories
+                .iter()
+                .find(|category| &category.id == id)
+                .and_then(|category| category.parent_id())
+                .map(|id| id.to_string())
+        });
+        let relationships = &mut transaction.relationships;
+        relationships.category.links = category_id.as_ref().map(|id| RelationshipLinks {
+            self_: Some(format!(
+                "{}/transactions/{}/relationships/category",
+                BASE_URL, transaction.id
+            )),
+            related: Some(format!("{}/categories/{}", BASE_URL, id)),
+        });
+        relationships.category.data = category_id.map(|id| identifier("categories", &id));
+        relationships.parent_category.links = parent_id.as_ref().map(|id| RelationshipLinks {
+            self_: None,
+            related: Some(format!("{}/categories/{}", BASE_URL, id)),
+        });
+        relationships.parent_category.data = parent_id.map(|id| identifier("categories", &id));
+    }
+
+    fn authorized(&self, request: &HttpRequest) -> bool {
+        match &self.api_key {
+            None => !request.token.expose().is_empty(),
+            Some(api_key) => request.token.expose() == api_key,
+        }
+    }
+
+    fn handle(&mut self, request: HttpRequest) -> Result<HttpResponse, HttpResponse> {
+        if !self.authorized(&request) {
+            return Err(error(
+                401,
+                "Not Authorized",
+                "The request was not authenticated because no valid credential was found in the \
+                 Authorization header, or the Authorization header was not present.",
+            ));
+        }
+        let (path, query) = split_url(&request)?;
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (request.method, segments.as_slice()) {
+            (Method::Get, ["util", "ping"]) => Ok(ok(json!({
+                "meta": { "id": "00000000-0000-0000-0000-000000000000", "statusEmoji": "⚡️" }
+            }))),
+            (Method::Get, ["accounts"]) => self.list_accounts(&path, &query),
+            (Method::Get, ["accounts", id]) => {
+                check_query(&query, &[])?;
+                let account = self.find_account(id)?;
+                Ok(ok(json!({ "data": account })))
+            }
+            (Method::Get, ["accounts", id, "transactions"]) => {
+                self.find_account(id)?;
+                self.list_transactions(&path, &query, Some(id))
+            }
+            (Method::Get, ["categories"]) => self.list_categories(&query),
+            (Method::Get, ["categories", id]) => {
+                check_query(&query, &[])?;
+                let category = self.find_category(id)?;
+                Ok(ok(json!({ "data": self.render_category(category) })))
+            }
+            (Method::Get, ["tags"]) => {
+                check_query(&query, &["page[size]", "page[after]"])?;
+                let tags: Vec<Tag> = self.tags.iter().map(|id| render_tag(id)).collect();
+                page(tags, &path, &query, |tag| &tag.id)
+            }
+            (Method::Get, ["transactions"]) => self.list_transactions(&path, &query, None),
+            (Method::Get, ["transactions", id]) => {
+                check_query(&query, &[])?;
+                let transaction = self.find_transaction(id)?;
+                Ok(ok(json!({ "data": transaction })))
+            }
+            (Method::Patch, ["transactions", id, "relationships", "category"]) => {
+                let payload: Payload<Option<ResourceIdentifier>> = parse_body(&request)?;
+                self.categorize(id, payload.data)
+            }
+            (Method::Post, ["transactions", id, "relationships", "tags"]) => {
+                let payload: Payload<Vec<ResourceIdentifier>> = parse_body(&request)?;
+                self.update_tags(id, payload.data, true)
+            }
+            (Method::Delete, ["transactions", id, "relationships", "tags"]) => {
+                let payload: Payload<Vec<ResourceIdentifier>> = parse_body(&request)?;
+                self.update_tags(id, payload.data, false)
+            }
+            (Method::Get, ["webhooks"]) => {
+                check_query(&query, &["page[size]", "page[after]"])?;
+                page(self.webhooks.to_vec(), &path, &query, |webhook| &webhook.id)
+            }
+            (Method::Post, ["webhooks"]) => self.create_webhook(&request),
+            (Method::Get, ["webhooks", id]) => {
+                let webhook = self.find_webhook(id)?;
+                Ok(ok(json!({ "data": webhook })))
+            }
+            (Method::Delete, ["webhooks", id]) => {
+                self.find_webhook(id)?;
+                self.webhooks.retain(|webhook| webhook.id != *id);
+                self.webhook_logs.retain(|(webhook_id, _)| webhook_id != id);
+                Ok(no_content())
+            }
+            (Method::Post, ["webhooks", id, "ping"]) => self.ping_webhook(id),
+            (Method::Get, ["webhooks", id, "logs"]) => {
+                check_query(&query, &["page[size]", "page[after]"])?;
+                self.find_webhook(id)?;
+                let logs: Vec<WebhookDeliveryLog> = self
+                    .webhook_logs
+                    .iter()
+                    .rev()
+                    .filter(|(webhook_id, _)| webhook_id == id)
+                    .map(|(_, log)| log.clone())
+                    .collect();
+                page(logs, &path, &query, |log| &log.id)
+            }
+            _ => Err(not_found(&path)),
+        }
+    }
+
+    fn find_webhook(&self, id: &str) -> Result<&Webhook, HttpResponse> {
+        self.webhooks
+            .iter()
+            .find(|webhook| webhook.id == id)
+            .ok_or_else(|| not_found(&format!("/webhooks/{}", id)))
+    }
+
+    fn create_webhook(&mut self, request: &HttpRequest) -> Result<HttpResponse, HttpResponse> {
+        let payload: Payload<serde_json::Value> = parse_body(request)?;
+        let attributes = &payload.data["attributes"];
+        let url = match attributes["url"].as_str() {
+            Some(url) if url.starts_with("http") => url.to_string(),
+            _ => return Err(invalid_parameter("url")),
+        };
+        let id = format!("webhook-{}", self.webhooks.len() + 1);
+        let webhook = Webhook {
+            resource_type: "webhooks".to_string(),
+            id: id.to_string(),
+            attributes: WebhookAttributes {
+                url,
+                description: attributes["description"].as_str().map(String::from),
+                secret_key: None,
+                created_at: chrono::Utc::now().fixed_offset(),
+            },
+            relationships: WebhookRelationships {
+                logs: WebhookLogsRelationship {
+                    links: Some(RelationshipLinks {
+                        self_: None,
+                        related: Some(format!("{}/webhooks/{}/logs", BASE_URL, id)),
+                    }),
+                },
+            },
+            links: Some(HashMap::from([(
+                "self".to_string(),
+                format!("{}/webhooks/{}", BASE_URL, id),
+            )])),
+        };
+        self.webhooks.push(webhook.clone());
+        let mut created = webhook;
+        created.attributes.secret_key = Some(format!("secret-{}", id));
+        Ok(with_status(201, json!({ "data": created })))
+    }
+
+    fn ping_webhook(&mut self, id: &str) -> Result<HttpResponse, HttpResponse> {
+        self.find_webhook(id)?;
+        let count = self.webhook_logs.len() + 1;
+        let event = WebhookEvent {
+            resource_type: "webhook-events".to_string(),
+            id: format!("event-{}", count),
+            attributes: WebhookEventAttributes {
+                event_type: WebhookEventType::Ping,
+                created_at: chrono::Utc::now().fixed_offset(),
+            },
+            relationships: WebhookEventRelationships {
+                webhook: WebhookEventRelationship {
+                    data: identifier("webhooks", id),
+                    links: Some(RelationshipLinks {
+                        self_: None,
+                        related: Some(format!("{}/webhooks/{}", BASE_URL, id)),
+                    }),
+                },
+                transaction: None,
+            },
+        };
+        let body = json!({ "data": event });
+        let log = WebhookDeliveryLog {
+            resource_type: "webhook-delivery-logs".to_string(),
+            id: format!("log-{}", count),
+            attributes: WebhookDeliveryLogAttributes {
+                request: WebhookDeliveryRequest {
+                    body: body.to_string(),
+                },
+                response: Some(WebhookDeliveryResponse {
+                    status_code: 200,
+                    body: "{}".to_string(),
+                }),
+                delivery_status: WebhookDeliveryStatus::Delivered,
+                created_at: event.attributes.created_at,
+            },
+            relationships: WebhookDeliveryLogRelationships {
+                webhook_event: WebhookDeliveryLogEventRelationship {
+                    data: identifier("webhook-events", &event.id),
+                },
+            },
+        };
+        self.webhook_logs.push((id.to_string(), log));
+        Ok(with_status(201, body))
+    }
+
+    fn find_account(&self, id: &str) -> Result<&Account, HttpResponse> {
+        self.accounts
+            .iter()
+            .find(|account| account.id == id)
+            .ok_or_else(|| not_found(&format!("/accounts/{}", id)))
+    }
+
+    fn find_category(&self, id: &str) -> Result<&Category, HttpResponse> {
+        self.categories
+            .iter()
+            .find(|category| category.id == id)
+            .ok_or_else(|| not_found(&format!("/categories/{}", id)))
+    }
+
+    fn find_transaction(&self, id: &str) -> Result<&Transaction, HttpResponse> {
+        self.transactions
+            .iter()
+            .find(|transaction| transaction.id == id)
+            .ok_or_else(|| not_found(&format!("/transactions/{}", id)))
+    }
+
+    fn list_accounts(
+        &self,
+        path: &str,
+        query: &[(String, String)],
+    ) -> Result<HttpResponse, HttpResponse> {
+        check_query(
+            query,
+            &[
+                "page[size]",
+                "page[after]",
+                "filter[accountType]",
+                "filter[ownershipType]",
+            ],
+        )?;
+        let account_type: Option<AccountType> = filter(query, "filter[accountType]")?;
+        let ownership_type: Option<OwnershipType> = filter(query, "filter[ownershipType]")?;
+        let accounts: Vec<&Account> = self
+            .accounts
+            .iter()
+            .filter(|account| {
+                account_type.is_none_or(|t| account.attributes.account_type == t)
+                    && ownership_type.is_none_or(|t| account.attributes.ownership_type == t)
+            })
+            .collect();
+        page(accounts, path, query, |account| &account.id)
+    }
+
+    fn list_categories(&self, query: &[(String, String)]) -> Result<HttpResponse, HttpResponse> {
+        check_query(query, &["filter[parent]"])?;
+        let parent = query_value(query, "filter[parent]");
+        if let Some(parent) = parent {
+            self.find_category(parent)?;
+        }
+        let categories: Vec<Category> = self
+            .categories
+            .iter()
+            .filter(|category| {
+                parent.is_none() || category.parent_id().map(|id| id.as_str()) == parent
+            })
+            .map(|category| self.render_category(category))
+            .collect();
+        Ok(ok(
+            json!({ "data": categories, "links": { "prev": null, "next": null } }),
+        ))
+    }
+
+    fn list_transactions(
+        &self,
+        path: &str,
+        query: &[(String, String)],
+        account_id: Option<&str>,
+    ) -> Result<HttpResponse, HttpResponse> {
+        check_query(
+            query,
+            &[
+                "page[size]",
+                "page[after]",
+                "filter[status]",
+                "filter[since]",
+                "filter[until]",
+                "filter[category]",
+                "filter[tag]",
+            ],
+        )?;
+        let status: Option<TransactionStatus> = filter(query, "filter[status]")?;
+        let since = date_time_filter(query, "filter[since]")?;
+        let until = date_time_filter(query, "filter[until]")?;
+        let category = query_value(query, "filter[category]");
+        if let Some(category) = category {
+            self.find_category(category)?;
+        }
+        let tag = query_value(query, "filter[tag]");
+        let transactions: Vec<&Transaction> = self
+            .transactions
+            .iter()
+            .filter(|transaction| {
+                let created_at = transaction.attributes.created_at;
+                account_id.is_none_or(|id| transaction.account_id() == id)
+                    && status.is_none_or(|s| transaction.attributes.status == s)
+                    && since.is_none_or(|since| created_at >= since)
+                    && until.is_none_or(|until| created_at < until)
+                    && category.is_none_or(|id| {
+                        transaction.category_id().map(|c| c.as_str()) == Some(id)
+                            || transaction.parent_category_id().map(|c| c.as_str()) == Some(id)
+                    })
+                    && tag.is_none_or(|tag| transaction.tag_ids().iter().any(|t| *t == tag))
+            })
+            .collect();
+        page(transactions, path, query, |transaction| &transaction.id)
+    }
+
+    fn categorize(
+        &mut self,
+        id: &str,
+        category: Option<ResourceIdentifier>,
+    ) -> Result<HttpResponse, HttpResponse> {
+        let mut transaction = self.find_transaction(id)?.clone();
+        if !transaction.attributes.is_categorizable {
+            return Err(error(
+                403,
+                "Forbidden",
+                "This transaction cannot be categorized.",
+            ));
+        }
+        if let Some(category) = &category {
+            if category.resource_type != "categories" || self.find_category(&category.id).is_err() {
+                return Err(error(
+                    422,
+                    "Invalid Request Parameter",
+                    &format!("{} is not a valid category.", category.id),
+                ));
+            }
+        }
+        self.set_category(&mut transaction, category.map(|category| category.id));
+        self.add_transaction(transaction);
+        Ok(no_content())
+    }
+
+    fn update_tags(
+        &mut self,
+        id: &str,
+        tags: Vec<ResourceIdentifier>,
+        add: bool,
+    ) -> Result<HttpResponse, HttpResponse> {
+        let mut transaction = self.find_transaction(id)?.clone();
+        if let Some(tag) = tags.iter().find(|tag| tag.resource_type != "tags") {
+            return Err(error(
+                422,
+                "Invalid Request Parameter",
+                &format!("{} is not a tag.", tag.id),
+            ));
+        }
+        let data = &mut transaction.relationships.tags.data;
+        for tag in tags {
+            data.retain(|existing| existing.id != tag.id);
+            if add {
+                data.push(tag);
+            }
+        }
+        self.add_transaction(transaction);
+        Ok(no_content())
+    }
+
+    /// A category with its `children` worked out from the other seeded categories.
+    fn render_category(&self, category: &Category) -> Category {
+        let mut category = category.clone();
+        let children: Vec<ResourceIdentifier> = self
+            .categories
+            .iter()
+            .filter(|child| child.parent_id() == Some(&category.id))
+            .map(|child| identifier("categories", &child.id))
+            .collect();
+        category.relationships.children.data = Some(children);
+        category
+    }
+}
+
+/// The path after `BASE_URL`, and the query parameters from both the URL and `request.query`.
+fn split_url(request: &HttpRequest) -> Result<(String, Vec<(String, String)>), HttpResponse> {
+    let rest = match request.url.strip_prefix(BASE_URL) {
+        None => return Err(not_found(&request.url)),
+        Some(rest) => rest,
+    };
+    let (path, query_string) = match rest.split_once('?') {
+        None => (rest, ""),
+        Some((path, query_string)) => (path, query_string),
+    };
+    let mut query: Vec<(String, String)> = query_string
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            None => (percent_decode(pair), String::new()),
+            Some((name, value)) => (percent_decode(name), percent_decode(value)),
+        })
+        .collect();
+    query.extend_from_slice(&request.query);
+    Ok((path.to_string(), query))
+}
+
+fn query_value<'a>(query: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    query
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Rejects parameters the endpoint does not know, as the real API does.
+fn check_query(query: &[(String, String)], allowed: &[&str]) -> Result<(), HttpResponse> {
+    match query
+        .iter()
+        .find(|(key, _)| !allowed.contains(&key.as_str()))
+    {
+        None => Ok(()),
+        Some((key, _)) => Err(invalid_parameter(key)),
+    }
+}
+
+/// Parses a filter into one of the enums in `models`, e.g. `SETTLED` into `TransactionStatus`.
+fn filter<T: DeserializeOwned>(
+    query: &[(String, String)],
+    name: &str,
+) -> Result<Option<T>, HttpResponse> {
+    match query_value(query, name) {
+        None => Ok(None),
+        Some(value) => serde_json::from_value(json!(value))
+            .map(Some)
+            .map_err(|_| invalid_parameter(name)),
+    }
+}
+
+fn date_time_filter(
+    query: &[(String, String)],
+    name: &str,
+) -> Result<Option<DateTime<FixedOffset>>, HttpResponse> {
+    match query_value(query, name) {
+        None => Ok(None),
+        Some(value) => DateTime::parse_from_rfc3339(value)
+            .map(Some)
+            .map_err(|_| invalid_parameter(name)),
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(request: &HttpRequest) -> Result<T, HttpResponse> {
+    let body = request.body.as_deref().unwrap_or("");
+    serde_json::from_str(body).map_err(|e| error(400, "Bad Request", &e.to_string()))
+}
+
+/// One page of `items`. The cursor in `page[after]` is the id of the last item of the previous
+/// page, and the `next` link keeps the other query parameters.
+fn page<T: Serialize>(
+    items: Vec<T>,
+    path: &str,
+    query: &[(String, String)],
+    id: fn(&T) -> &String,
+) -> Result<HttpResponse, HttpResponse> {
+    let size = match query_value(query, "page[size]") {
+        None => DEFAULT_PAGE_SIZE,
+        Some(size) => match size.parse::<usize>() {
+            Ok(size) if size > 0 => size,
+            _ => return Err(invalid_parameter("page[size]")),
+        },
+    };
+    let start = match query_value(query, "page[after]") {
+        None => 0,
+        Some(after) => match items.iter().position(|item| id(item) == after) {
+            Some(position) => position + 1,
+            None => return Err(invalid_parameter("page[after]")),
+        },
+    };
+    let end = items.len().min(start + size);
+    let next = match end < items.len() {
+        false => None,
+        true => {
+            let mut params: Vec<(String, String)> = query
+                .iter()
+                .filter(|(key, _)| key != "page[after]" && key != "page[size]")
+                .cloned()
+                .collect();
+            params.push(("page[after]".to_string(), id(&items[end - 1]).to_string()));
+            params.push(("page[size]".to_string(), size.to_string()));
+            Some(format!("{}{}?{}", BASE_URL, path, encode_query(&params)))
+        }
+    };
+    let data: Vec<&T> = items[start..end].iter().collect();
+    Ok(ok(
+        json!({ "data": data, "links": { "prev": null, "next": next } }),
+    ))
+}
+
+fn encode_query(params: &[(String, String)]) -> String {
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", percent_encode(name), percent_encode(value)))
+        .collect::<Vec<String>>()
+        .join("&")
+}
+
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn ok(body: serde_json::Value) -> HttpResponse {
+    with_status(200, body)
+}
+
+fn with_status(status: u16, body: serde_json::Value) -> HttpResponse {
+    HttpResponse {
+        status,
+        headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+        body: body.to_string(),
+    }
+}
+
+fn no_content() -> HttpResponse {
+    HttpResponse {
+        status: 204,
+        headers: Vec::new(),
+        body: String::new(),
+    }
+}
+
+/// An error in the format used by the API, e.g. `{"errors": [{"status": "404", ...}]}`.
+fn error(status: u16, title: &str, detail: &str) -> HttpResponse {
+    HttpResponse {
+        status,
+        headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+        body: json!({
+            "errors": [{ "status": status.to_string(), "title": title, "detail": detail }]
+        })
+        .to_string(),
+    }
+}
+
+fn not_found(path: &str) -> HttpResponse {
+    error(
+        404,
+        "Not Found",
+        &format!("The resource at {} could not be found.", path),
+    )
+}
+
+fn invalid_parameter(name: &str) -> HttpResponse {
+    HttpResponse {
+        status: 400,
+        headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+        body: json!({
+            "errors": [{
+                "status": "400",
+                "title": "Invalid Request Parameter",
+                "detail": format!("The {} parameter is not valid for this request.", name),
+                "source": { "parameter": name }
+            }]
+        })
+        .to_string(),
+    }
+}
+
+fn render_tag(id: &str) -> Tag {
+    Tag {
+        resource_type: "tags".to_string(),
+        id: id.to_string(),
+        relationships: TagRelationships {
+            transactions: TagTransactionRelationships {
+                links: RelationshipLinks {
+                    self_: None,
+                    related: Some(format!(
+                        "{}/transactions?filter%5Btag%5D={}",
+                        BASE_URL,
+                        percent_encode(id)
+                    )),
+                },
+            },
+        },
+    }
+}
+
+pub fn identifier(resource_type: &str, id: &str) -> ResourceIdentifier {
+    ResourceIdentifier {
+        resource_type: resource_type.to_string(),
+        id: id.to_string(),
+    }
+}
+
+/// An amount in Australian dollars, e.g. `money(-1050)` is -$10.50.
+pub fn money(value_in_base_units: i128) -> Money {
+    let sign = if value_in_base_units < 0 { "-" } else { "" };
+    let cents = value_in_base_units.abs();
+    Money {
+        currency_code: "AUD".to_string(),
+        value: format!("{}{}.{:02}", sign, cents / 100, cents % 100),
+        value_in_base_units,
+    }
+}
+
+/// An individually owned account created at the start of 2022.
+pub fn account(
+    id: &str,
+    display_name: &str,
+    account_type: AccountType,
+    balance_in_base_units: i128,
+) -> Account {
+    Account {
+        resource_type: "accounts".to_string(),
+        id: id.to_string(),
+        attributes: AccountAttributes {
+            display_name: display_name.to_string(),
+            account_type,
+            ownership_type: OwnershipType::Individual,
+            balance: money(balance_in_base_units),
+            created_at: DateTime::parse_from_rfc3339("2022-01-01T00:00:00+11:00").unwrap(),
+        },
+        relationships: AccountRelationships {
+            transactions: AccountTransactionsRelationships {
+                links: RelationshipLinks {
+                    self_: None,
+                    related: Some(format!("{}/accounts/{}/transactions", BASE_URL, id)),
+                },
+            },
+        },
+        links: HashMap::from([("self".to_string(), format!("{}/accounts/{}", BASE_URL, id))]),
+    }
+}
+
+pub fn category(id: &str, name: &str, parent_id: Option<&str>) -> Category {
+    Category {
+        resource_type: "categories".to_string(),
+        id: id.to_string(),
+        attributes: CategoryAttributes {
+            name: name.to_string(),
+        },
+        relationships: CategoryRelationships {
+            parent: ParentRelationship {
+                data: parent_id.map(|parent_id| identifier("categories", parent_id)),
+                links: parent_id.map(|parent_id| RelationshipLinks {
+                    self_: None,
+                    related: Some(format!("{}/categories/{}", BASE_URL, parent_id)),
+                }),
+            },
+            children: ChildRelationship {
+                data: Some(Vec::new()),
+                links: Some(RelationshipLinks {
+                    self_: None,
+                    related: Some(format!("{}/categories?filter%5Bparent%5D={}", BASE_URL, id)),
+                }),
+            },
+        },
+        links: Some(HashMap::from([(
+            "self".to_string(),
+            format!("{}/categories/{}", BASE_URL, id),
+        )])),
+    }
+}
+
+/// Parses an RFC 3339 date-time, e.g. `at("2022-10-01T09:00:00+10:00")`. Panics if it is invalid.
+#[cfg(test)]
+pub(crate) fn at(date_time: &str) -> DateTime<FixedOffset> {
+    DateTime::parse_from_rfc3339(date_time).unwrap()
+}
+
+/// Like `transaction`, but held rather than settled.
+#[cfg(test)]
+pub(crate) fn held_transaction(
+    id: &str,
+    account_id: &str,
+    description: &str,
+    amount_in_base_units: i128,
+    created_at: DateTime<FixedOffset>,
+) -> Transaction {
+    let mut transaction = transaction(
+        id,
+        account_id,
+        description,
+        amount_in_base_units,
+        created_at,
+    );
+    transaction.attributes.status = TransactionStatus::Held;
+    transaction.attributes.settled_at = None;
+    transaction
+}
+
+/// A settled, uncategorized and untagged transaction on `account_id`.
+pub fn transaction(
+    id: &str,
+    account_id: &str,
+    description: &str,
+    amount_in_base_units: i128,
+    created_at: DateTime<FixedOffset>,
+) -> Transaction {
+    Transaction {
+        resource_type: "transactions".to_string(),
+        id: id.to_string(),
+        attributes: TransactionAttributes {
+            status: TransactionStatus::Settled,
+            raw_text: None,
+            description: description.to_string(),
+            message: None,
+            is_categorizable: true,
+            hold_info: None,
+            round_up: None,
+            cashback: None,
+            amount: money(amount_in_base_units),
+            foreign_amount: None,
+            card_purchase_method: None,
+            settled_at: Some(created_at),
+            created_at,
+        },
+        relationships: TransactionRelationships {
+            account: TransactionAccountRelationship {
+                data: identifier("accounts", account_id),
+                links: Some(RelationshipLinks {
+                    self_: None,
+                    related: Some(format!("{}/accounts/{}", BASE_URL, account_id)),
+                }),
+            },
+            transfer_account: TransactionTransferAccountRelationship {
+                data: None,
+                links: None,
+            },
+            category: TransactionCategoryRelationship {
+                data: None,
+                links: None,
+            },
+            parent_category: TransactionCategoryRelationship {
+                data: None,
+                links: None,
+            },
+            tags: TransactionTagsRelationship {
+                data: Vec::new(),
+                links: Some(RelationshipLinks {
+                    self_: Some(format!(
+                        "{}/transactions/{}/relationships/tags",
+                        BASE_URL, id
+                    )),
+                    related: None,
+                }),
+            },
+        },
+        links: Some(HashMap::from([(
+            "self".to_string(),
+            format!("{}/transactions/{}", BASE_URL, id),
+        )])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::request_sender::ApiRequest;
+    use crate::testing::*;
+    use crate::token::Token;
+
+    fn seeded() -> MockUpApi {
+        let mut held = held_transaction(
+            "t3",
+            "spending",
+            "Pizza",
+            -2500,
+            at("2022-10-03T19:00:00+11:00"),
+        );
+        held.relationships.tags.data = vec![identifier("tags", "Pizza Night")];
+        let mut takeaway = transaction(
+            "t2",
+            "spending",
+            "Burgers",
+            -1500,
+            at("2022-10-02T12:00:00+11:00"),
+        );
+        takeaway.relationships.category.data = Some(identifier("categories", "takeaway"));
+        MockUpApi::new()
+            .account(account(
+                "spending",
+                "Spending",
+                AccountType::Transactional,
+                10000,
+            ))
+            .account(account("savings", "Savings", AccountType::Saver, 50000))
+            .category(category("good-life", "Good Life", None))
+            .category(category("takeaway", "Takeaway", Some("good-life")))
+            .category(category(
+                "games-and-software",
+                "Games & Software",
+                Some("good-life"),
+            ))
+            .tag("Holiday")
+            .transaction(transaction(
+                "t1",
+                "savings",
+                "Interest",
+                100,
+                at("2022-10-01T09:00:00+10:00"),
+            ))
+            .transaction(takeaway)
+            .transaction(held)
+    }
+
+    #[tokio::test]
+    async fn lists_and_filters() {
+        let mock = seeded();
+        let client = mock.client();
+
+        let transactions = client
+            .list_transactions()
+            .page_size(1)
+            .send()
+            .await
+            .unwrap();
+        let ids: Vec<&String> = transactions.iter().map(|t| &t.id).collect();
+        assert_eq!(ids, vec!["t3", "t2", "t1"]);
+        assert_eq!(mock.get_requests().len(), 3);
+        assert!(mock.get_requests()[1].url.contains("page%5Bafter%5D=t3"));
+
+        let held = client
+            .list_transactions()
+            .status(TransactionStatus::Held)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(held[0].id, "t3");
+        let good_life = client
+            .list_transactions()
+            .category(category("good-life", "Good Life", None))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(good_life[0].id, "t2");
+        assert_eq!(good_life[0].parent_category_id().unwrap(), "good-life");
+        let on_date = client
+            .list_transactions()
+            .since(at("2022-10-02T00:00:00+11:00"))
+            .until(at("2022-10-03T00:00:00+11:00"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(on_date.len(), 1);
+
+        let tags = client.list_tags().send().await.unwrap();
+        assert_eq!(tags.len(), 2);
+        let pizza = tags
+            .into_iter()
+            .find(|tag| tag.id == "Pizza Night")
+            .unwrap();
+        assert_eq!(pizza.transactions(&client).await.unwrap()[0].id, "t3");
+
+        let savers = client
+            .list_accounts()
+            .account_type(AccountType::Saver)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(savers.len(), 1);
+        assert_eq!(savers[0].transactions(&client).await.unwrap()[0].id, "t1");
+
+        let good_life = client
+            .retrieve_category("good-life".to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(good_life.children(&client).await.unwrap().len(), 2);
+        assert!(client
+            .retrieve_transaction("missing".to_string())
+            .send()
+            .await
+            .unwrap_err()
+            .contains("Not Found"));
+    }
+
+    #[tokio::test]
+    async fn categorizes_and_tags() {
+        let mock = seeded();
+        let client = mock.client();
+
+        client
+            .categorize_transaction("t3".to_string(), Some("takeaway".to_string()))
+            .send()
+            .await
+            .unwrap();
+        let t3 = mock.get_transaction("t3").unwrap();
+        assert_eq!(t3.category_id().unwrap(), "takeaway");
+        assert_eq!(t3.parent_category_id().unwrap(), "good-life");
+        client
+            .categorize_transaction("t3".to_string(), None)
+            .send()
+            .await
+            .unwrap();
+        assert!(mock.get_transaction("t3").unwrap().category_id().is_none());
+        assert!(client
+            .categorize_transaction("t3".to_string(), Some("nope".to_string()))
+            .send()
+            .await
+            .is_err());
+
+        client
+            .add_tag_to_transaction(
+                "t1".to_string(),
+                vec!["Holiday".to_string(), "Fun".to_string()],
+            )
+            .send()
+            .await
+            .unwrap();
+        client
+            .remove_tag_from_transaction("t1".to_string(), vec!["Holiday".to_string()])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(mock.get_transaction("t1").unwrap().tag_ids(), vec!["Fun"]);
+        assert!(mock.get_tags().contains(&"Fun".to_string()));
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_requests() {
+        let mock = seeded().api_key(&"secret".to_string());
+        let client = mock.client();
+        assert!(client.list_accounts().send().await.is_ok());
+
+        let stranger = UpClient::new(&"wrong".to_string()).transport(mock.clone());
+        assert!(stranger
+            .list_accounts()
+            .send()
+            .await
+            .unwrap_err()
+            .contains("Not Authorized"));
+
+        let token = Token::new("secret");
+        let mut request = HttpRequest::get(&format!("{}/transactions", BASE_URL), &token);
+        request
+            .query
+            .push(("filter[tag".to_string(), "Holiday".to_string()));
+        let response = mock.send(request).await.unwrap();
+        assert_eq!(response.status, 400);
+    }
+
+    #[tokio::test]
+    async fn pings_and_manages_webhooks() {
+        let mock = MockUpApi::new();
+        let client = mock.client();
+        assert_eq!(client.ping().send().await.unwrap().status_emoji, "⚡️");
+
+        let created = client
+            .create_webhook("https://example.com/up".to_string())
+            .description("Alerts")
+            .send()
+            .await
+            .unwrap();
+        assert!(created.attributes.secret_key.is_some());
+        assert_eq!(created.attributes.description.as_deref(), Some("Alerts"));
+        let listed = client.list_webhooks().send().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].attributes.secret_key, None);
+
+        let event = client.ping_webhook(created.id.to_string()).send().await.unwrap();
+        assert_eq!(event.attributes.event_type, WebhookEventType::Ping);
+        let logs = client
+            .list_webhook_logs(created.id.to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(logs[0].attributes.delivery_status, WebhookDeliveryStatus::Delivered);
+        assert_eq!(logs[0].relationships.webhook_event.data.id, event.id);
+
+        client
+            .delete_webhook(created.id.to_string())
+            .send()
+            .await
+            .unwrap();
+        assert!(mock.get_webhooks().is_empty());
+        assert!(client.retrieve_webhook(created.id).send().await.is_err());
+    }
+
+    #[test]
+    fn percent_encoding_round_trips() {
+        assert_eq!(percent_encode("Pizza Night"), "Pizza%20Night");
+        assert_eq!(percent_decode("Pizza+Night"), "Pizza Night");
+        assert_eq!(percent_decode("filter%5Btag%5D"), "filter[tag]");
+        assert_eq!(percent_decode("100%"), "100%");
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+use std::io::BufRead;
+use std::path::Path;
+use zeroize::Zeroize;
+
+/// The environment variable read by `Token::from_env`.
+pub const TOKEN_ENV_VAR: &str = "UP_API_TOKEN";
+
+/// A personal access token for the Up API.
+///
+/// The token is overwritten with zeros when it is dropped and is never shown by `Debug` or
+/// `Display`. An `UpClient` keeps a single copy shared by its clones and endpoints, and each
+/// `HttpRequest` holds its own copy, which is zeroized in the same way.
+#[derive(Clone)]
+pub struct Token {
+    secret: String,
+}
+
+impl Token {
+    pub fn new(token: &str) -> Token {
+        Token {
+            secret: token.trim().to_string(),
+        }
+    }
+
+    /// Reads the token from the `UP_API_TOKEN` environment variable.
+    pub fn from_env() -> Result<Token, String> {
+        Token::from_var(TOKEN_ENV_VAR)
+    }
+
+    fn from_var(name: &str) -> Result<Token, String> {
+        match std::env::var(name) {
+            Ok(mut value) => {
+                let token = Token::new(&value);
+                value.zeroize();
+                Token::non_empty(token, name)
+            }
+            Err(_) => Err(format!("{} is not set.", name)),
+        }
+    }
+
+    /// Reads the token from a file. On Unix the file must not be accessible by the group or
+    /// other users (e.g. `chmod 600`).
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Token, String> {
+        let path = path.as_ref();
+        check_permissions(path)?;
+        let mut contents =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let token = Token::new(&contents);
+        contents.zeroize();
+        Token::non_empty(token, &path.display().to_string())
+    }
+
+    /// Reads the token from the first line of standard input.
+    pub fn from_stdin() -> Result<Token, String> {
+        Token::from_reader(std::io::stdin().lock())
+    }
+
+    /// Reads the token from the first line of `reader`.
+    pub fn from_reader<R: BufRead>(mut reader: R) -> Result<Token, String> {
+        let mut line = String::new();
+        reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        let token = Token::new(&line);
+        line.zeroize();
+        Token::non_empty(token, "The input")
+    }
+
+    fn non_empty(token: Token, source: &str) -> Result<Token, String> {
+        match token.secret.is_empty() {
+            true => Err(format!("{} does not contain a token.", source)),
+            false => Ok(token),
+        }
+    }
+
+    /// The token itself. Take care not to log or store the returned value.
+    pub fn expose(&self) -> &str {
+        &self.secret
+    }
+}
+
+impl From<&String> for Token {
+    fn from(token: &String) -> Token {
+        Token::new(token)
+    }
+}
+
+impl Drop for Token {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl PartialEq for Token {
+    fn eq(&self, other: &Token) -> bool {
+        self.secret == other.secret
+    }
+}
+
+impl Eq for Token {}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Token(REDACTED)")
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("REDACTED")
+    }
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    let metadata = std::fs::metadata(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mode = metadata.permissions().mode();
+    if mode & 0o077 != 0 {
+        return Err(format!(
+            "{} can be accessed by other users (mode {:o}). Restrict it with `chmod 600`.",
+            path.display(),
+            mode & 0o777
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::token::*;
+
+    #[test]
+    fn redacts_the_token() {
+        let token = Token::new("up:yeah:secret\n");
+        assert_eq!(token.expose(), "up:yeah:secret");
+        assert_eq!(format!("{:?}", token), "Token(REDACTED)");
+        assert_eq!(format!("{}", token), "REDACTED");
+    }
+
+    #[test]
+    fn loads_from_a_reader_and_the_environment() {
+        let token = Token::from_reader("up:yeah:secret\nmore".as_bytes()).unwrap();
+        assert_eq!(token.expose(), "up:yeah:secret");
+        assert!(Token::from_reader("\n".as_bytes()).is_err());
+
+        std::env::set_var("UPRS_TEST_TOKEN", "up:yeah:env");
+        assert_eq!(
+            Token::from_var("UPRS_TEST_TOKEN").unwrap().expose(),
+            "up:yeah:env"
+        );
+        assert!(Token::from_var("UPRS_TEST_TOKEN_UNSET").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn checks_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!("uprs-token-{}", std::process::id()));
+        std::fs::write(&path, "up:yeah:file\n").unwrap();
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(Token::from_file(&path).unwrap_err().contains("chmod 600"));
+
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        assert_eq!(Token::from_file(&path).unwrap().expose(), "up:yeah:file");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
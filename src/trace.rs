@@ -0,0 +1,200 @@
+use crate::api_endpoints::BASE_URL;
+use crate::transport::{HttpRequest, HttpResponse};
+
+/// A `tracing` span for a logical request or for one page of a paginated request. Without the
+/// `tracing` feature this does nothing.
+///
+/// Only the method, the URL and the outcome are recorded. Headers, and so the `Authorization`
+/// header, never are. The client does not retry requests yet, so `retries` is always 0.
+pub(crate) struct Trace {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "tracing")]
+    started: std::time::Instant,
+}
+
+/// The URL relative to `BASE_URL`, e.g. `/transactions`.
+#[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+fn endpoint(url: &str) -> &str {
+    let url = url.strip_prefix(BASE_URL).unwrap_or(url);
+    url.split('?').next().unwrap_or(url)
+}
+
+#[cfg(feature = "tracing")]
+impl Trace {
+    pub(crate) fn request(request: &HttpRequest) -> Trace {
+        Trace {
+            span: tracing::debug_span!(
+                "uprs.request",
+                endpoint = endpoint(&request.url),
+                method = ?request.method,
+                status = tracing::field::Empty,
+                duration_ms = tracing::field::Empty,
+                retries = 0,
+                pages = tracing::field::Empty,
+                items = tracing::field::Empty,
+            ),
+            started: std::time::Instant::now(),
+        }
+    }
+
+    pub(crate) fn page(&self, request: &HttpRequest, page: usize) -> Trace {
+        Trace {
+            span: tracing::debug_span!(
+                parent: &self.span,
+                "uprs.page",
+                endpoint = endpoint(&request.url),
+                url = request.url.as_str(),
+                page,
+                status = tracing::field::Empty,
+                duration_ms = tracing::field::Empty,
+                retries = 0,
+                items = tracing::field::Empty,
+            ),
+            started: std::time::Instant::now(),
+        }
+    }
+
+    pub(crate) fn response(&self, response: &Result<HttpResponse, String>) {
+        if let Ok(response) = response {
+            self.span.record("status", response.status);
+        }
+    }
+
+    pub(crate) fn pages(&self, pages: usize) {
+        self.span.record("pages", pages);
+    }
+
+    /// Records the duration and item count and emits an event, so that subscribers that only
+    /// print events still show the request.
+    pub(crate) fn finish<T>(&self, result: &Result<T, String>, items: usize) {
+        let duration_ms = self.started.elapsed().as_millis() as u64;
+        self.span.record("duration_ms", duration_ms);
+        match result {
+            Ok(_) => {
+                self.span.record("items", items);
+                tracing::debug!(parent: &self.span, duration_ms, items, "finished");
+            }
+            Err(error) => tracing::warn!(parent: &self.span, duration_ms, error = %error, "failed"),
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+impl Trace {
+    pub(crate) fn request(_request: &HttpRequest) -> Trace {
+        Trace {}
+    }
+
+    pub(crate) fn page(&self, _request: &HttpRequest, _page: usize) -> Trace {
+        Trace {}
+    }
+
+    pub(crate) fn response(&self, _response: &Result<HttpResponse, String>) {}
+
+    pub(crate) fn pages(&self, _pages: usize) {}
+
+    pub(crate) fn finish<T>(&self, _result: &Result<T, String>, _items: usize) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::trace::endpoint;
+
+    #[test]
+    fn endpoint_is_relative_to_base_url() {
+        assert_eq!(
+            endpoint("https://api.up.com.au/api/v1/transactions?page%5Bafter%5D=1"),
+            "/transactions"
+        );
+        assert_eq!(endpoint("https://example.com/x"), "https://example.com/x");
+    }
+
+    /// Collects the names and fields of the spans and events it sees.
+    #[cfg(feature = "tracing")]
+    struct Collector {
+        seen: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+        next_id: std::sync::atomic::AtomicU64,
+    }
+
+    #[cfg(feature = "tracing")]
+    struct Fields<'a>(&'a mut String);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for Fields<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for Collector {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut line = span.metadata().name().to_string();
+            span.record(&mut Fields(&mut line));
+            self.seen.lock().unwrap().push(line);
+            let id = self
+                .next_id
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            tracing::span::Id::from_u64(id + 1)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+            let mut line = "record".to_string();
+            values.record(&mut Fields(&mut line));
+            self.seen.lock().unwrap().push(line);
+        }
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut line = "event".to_string();
+            event.record(&mut Fields(&mut line));
+            self.seen.lock().unwrap().push(line);
+        }
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn traces_requests_and_pages_without_the_token() {
+        use crate::models::AccountType;
+        use crate::request_sender::ApiRequest;
+        use crate::testing::{self, MockUpApi};
+
+        let mock = MockUpApi::new()
+            .api_key(&"up:yeah:secret".to_string())
+            .account(testing::account("a", "A", AccountType::Saver, 1))
+            .account(testing::account("b", "B", AccountType::Saver, 2));
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let collector = Collector {
+            seen: seen.clone(),
+            next_id: std::sync::atomic::AtomicU64::new(0),
+        };
+        tracing::subscriber::with_default(collector, || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let accounts = runtime
+                .block_on(mock.client().list_accounts().page_size(1).send())
+                .unwrap();
+            assert_eq!(accounts.len(), 2);
+        });
+
+        let seen = seen.lock().unwrap().join("\n");
+        assert!(seen.contains("uprs.request endpoint=\"/accounts\" method=Get"));
+        assert_eq!(seen.matches("uprs.page").count(), 2);
+        assert_eq!(seen.matches("retries=0").count(), 3);
+        assert!(seen.contains("record pages=2"));
+        assert!(seen.contains("record items=2"));
+        assert!(seen.contains("record status=200"));
+        assert!(!seen.contains("secret"));
+    }
+}
@@ -0,0 +1,414 @@
+use crate::token::Token;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "reqwest-client")]
+use std::collections::HashMap;
+#[cfg(feature = "reqwest-client")]
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    Get,
+    Post,
+    Patch,
+    Delete,
+}
+
+/// How long to wait for the different stages of a request. Unset stages wait indefinitely.
+///
+/// - `connect`: for a connection to the API to be established.
+/// - `read`: for the response to a single HTTP request once it has been sent. With `reqwest` this
+///   covers the whole exchange of that one request.
+/// - `total`: for a logical request to finish, including every page of a paginated one.
+///
+/// The `total` timeout is enforced by the client for any transport, while `connect` and `read` are
+/// handed to the transport with each `HttpRequest`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Timeouts {
+    pub connect: Option<Duration>,
+    pub read: Option<Duration>,
+    pub total: Option<Duration>,
+}
+
+impl Timeouts {
+    pub fn new() -> Timeouts {
+        Timeouts::default()
+    }
+
+    pub fn connect(mut self, timeout: Duration) -> Timeouts {
+        self.connect = Some(timeout);
+        self
+    }
+
+    pub fn read(mut self, timeout: Duration) -> Timeouts {
+        self.read = Some(timeout);
+        self
+    }
+
+    pub fn total(mut self, timeout: Duration) -> Timeouts {
+        self.total = Some(timeout);
+        self
+    }
+}
+
+/// A request as handed to a transport. The query parameters are kept apart from the URL so the
+/// transport can encode them.
+///
+/// The request holds the `Token` rather than an `Authorization` header, so every copy of it is
+/// zeroized on drop. Transports add the header only when they build the request they send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub query: Vec<(String, String)>,
+    pub token: Token,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    /// The `connect` and `read` timeouts the transport should apply.
+    pub timeouts: Timeouts,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, url: &str, token: &Token) -> HttpRequest {
+        HttpRequest {
+            method,
+            url: url.to_string(),
+            query: Vec::new(),
+            token: token.clone(),
+            headers: Vec::new(),
+            body: None,
+            timeouts: Timeouts::default(),
+        }
+    }
+
+    pub fn get(url: &str, token: &Token) -> HttpRequest {
+        HttpRequest::new(Method::Get, url, token)
+    }
+
+    pub fn query(mut self, query: &[(String, String)]) -> HttpRequest {
+        self.query.extend_from_slice(query);
+        self
+    }
+
+    /// Sets a JSON body.
+    pub fn body(mut self, body: String) -> HttpRequest {
+        self.body = Some(body);
+        self
+    }
+
+    /// The value of a header, matching its name case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpResponse {
+    /// The value of a header, matching its name case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Deserializes the body of a successful response, or returns the body as the error.
+    pub(crate) fn parse<T: serde::de::DeserializeOwned>(self) -> Result<T, String> {
+        match self.status {
+            200..=299 => serde_json::from_str(&self.body).map_err(|e| e.to_string()),
+            _ => Err(self.body),
+        }
+    }
+
+    /// Checks the status of a response that has no body, e.g. `204 No Content`.
+    pub(crate) fn no_content(self) -> Result<(), String> {
+        match self.status {
+            200..=299 => Ok(()),
+            _ => Err(self.body),
+        }
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Sends requests over HTTP. Every request made through an `UpClient` goes through its transport,
+/// so implementing this trait is the way to fake the API in tests, route requests through a
+/// custom client, or add instrumentation.
+///
+/// A transport only fails with `Err` when no response was received at all. Error statuses are
+/// returned as an `HttpResponse`.
+#[async_trait]
+pub trait HttpTransport: Send + Sync {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String>;
+
+    /// Sends a request for the `blocking` module without an async runtime. Transports that do
+    /// not implement it fail every blocking request.
+    #[cfg(feature = "blocking")]
+    fn send_blocking(&self, _request: HttpRequest) -> Result<HttpResponse, String> {
+        Err("This transport does not support blocking requests.".to_string())
+    }
+}
+
+/// The transport used when none has been given. Backed by `reqwest` if it is enabled.
+pub(crate) fn default_transport() -> Box<dyn HttpTransport> {
+    #[cfg(feature = "reqwest-client")]
+    return Box::new(ReqwestTransport::new());
+    #[cfg(not(feature = "reqwest-client"))]
+    return Box::new(NoTransport {});
+}
+
+/// A transport backed by a `reqwest::Client`.
+///
+/// `reqwest` only supports connect timeouts on the client, so a transport created with `new` keeps
+/// a client for each connect timeout it is asked for, and a blocking client for each with the
+/// `blocking` feature. A transport created with `from_client` always uses the given client and
+/// its connect timeout, and can only send blocking requests once it is given a blocking client
+/// with `blocking_client`.
+#[cfg(feature = "reqwest-client")]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+    owns_client: bool,
+    clients: Mutex<HashMap<Duration, reqwest::Client>>,
+    #[cfg(feature = "blocking")]
+    blocking_clients: Mutex<HashMap<Option<Duration>, reqwest::blocking::Client>>,
+    #[cfg(feature = "blocking")]
+    blocking_client: Option<reqwest::blocking::Client>,
+}
+
+#[cfg(feature = "reqwest-client")]
+impl ReqwestTransport {
+    pub fn new() -> ReqwestTransport {
+        ReqwestTransport {
+            owns_client: true,
+            ..ReqwestTransport::from_client(reqwest::Client::new())
+        }
+    }
+
+    /// Uses a client that has already been configured, e.g. with a proxy or client certificate.
+    pub fn from_client(client: reqwest::Client) -> ReqwestTransport {
+        ReqwestTransport {
+            client,
+            owns_client: false,
+            clients: Mutex::new(HashMap::new()),
+            #[cfg(feature = "blocking")]
+            blocking_clients: Mutex::new(HashMap::new()),
+            #[cfg(feature = "blocking")]
+            blocking_client: None,
+        }
+    }
+
+    /// Uses a blocking client that has already been configured for blocking requests.
+    #[cfg(feature = "blocking")]
+    pub fn blocking_client(mut self, client: reqwest::blocking::Client) -> ReqwestTransport {
+        self.blocking_client = Some(client);
+        self
+    }
+
+    fn client_for(&self, connect: Option<Duration>) -> Result<reqwest::Client, String> {
+        let connect = match connect {
+            Some(connect) if self.owns_client => connect,
+            _ => return Ok(self.client.clone()),
+        };
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(client) = clients.get(&connect) {
+            return Ok(client.clone());
+        }
+        let client = reqwest::Client::builder()
+            .connect_timeout(connect)
+            .build()
+            .map_err(|e| e.to_string())?;
+        clients.insert(connect, client.clone());
+        Ok(client)
+    }
+
+    /// The blocking client for a connect timeout, kept so its connections are reused.
+    #[cfg(feature = "blocking")]
+    fn blocking_client_for(
+        &self,
+        connect: Option<Duration>,
+    ) -> Result<reqwest::blocking::Client, String> {
+        let mut clients = self
+            .blocking_clients
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(client) = clients.get(&connect) {
+            return Ok(client.clone());
+        }
+        let mut builder = reqwest::blocking::Client::builder();
+        if let Some(connect) = connect {
+            builder = builder.connect_timeout(connect);
+        }
+        let client = builder.build().map_err(|e| e.to_string())?;
+        clients.insert(connect, client.clone());
+        Ok(client)
+    }
+}
+
+#[cfg(feature = "reqwest-client")]
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        ReqwestTransport::new()
+    }
+}
+
+#[cfg(feature = "reqwest-client")]
+fn reqwest_method(method: Method) -> reqwest::Method {
+    match method {
+        Method::Get => reqwest::Method::GET,
+        Method::Post => reqwest::Method::POST,
+        Method::Patch => reqwest::Method::PATCH,
+        Method::Delete => reqwest::Method::DELETE,
+    }
+}
+
+#[cfg(feature = "reqwest-client")]
+fn response_headers(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(feature = "reqwest-client")]
+#[async_trait]
+impl HttpTransport for ReqwestTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        let mut builder = self
+            .client_for(request.timeouts.connect)?
+            .request(reqwest_method(request.method), &request.url)
+            .query(&request.query);
+        if let Some(read) = request.timeouts.read {
+            builder = builder.timeout(read);
+        }
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        builder = builder.bearer_auth(request.token.expose());
+        if let Some(body) = request.body {
+            builder = builder
+                .header("Content-Type", "application/json")
+                .body(body);
+        }
+        let res = builder.send().await.map_err(|e| e.to_string())?;
+        Ok(HttpResponse {
+            status: res.status().as_u16(),
+            headers: response_headers(res.headers()),
+            body: res.text().await.map_err(|e| e.to_string())?,
+        })
+    }
+
+    #[cfg(feature = "blocking")]
+    fn send_blocking(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+        match (&self.blocking_client, self.owns_client) {
+            (Some(client), _) => send_blocking(client, request),
+            (None, true) => {
+                send_blocking(&self.blocking_client_for(request.timeouts.connect)?, request)
+            }
+            (None, false) => Err(
+                "A transport created with `from_client` needs a blocking client for blocking \
+                 requests. Give it one with `blocking_client`."
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+#[cfg(not(feature = "reqwest-client"))]
+struct NoTransport {}
+
+#[cfg(not(feature = "reqwest-client"))]
+#[async_trait]
+impl HttpTransport for NoTransport {
+    async fn send(&self, _request: HttpRequest) -> Result<HttpResponse, String> {
+        Err(
+            "No HTTP transport is available. Enable the `rustls-tls` or `native-tls` feature, \
+             or give the client a transport."
+                .to_string(),
+        )
+    }
+}
+
+/// Sends a request with the blocking `reqwest` client, applying the request's read timeout.
+#[cfg(feature = "blocking")]
+fn send_blocking(
+    client: &reqwest::blocking::Client,
+    request: HttpRequest,
+) -> Result<HttpResponse, String> {
+    let mut builder = client
+        .request(reqwest_method(request.method), &request.url)
+        .query(&request.query);
+    if let Some(read) = request.timeouts.read {
+        builder = builder.timeout(read);
+    }
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    builder = builder.bearer_auth(request.token.expose());
+    if let Some(body) = request.body {
+        builder = builder
+            .header("Content-Type", "application/json")
+            .body(body);
+    }
+    let res = builder.send().map_err(|e| e.to_string())?;
+    Ok(HttpResponse {
+        status: res.status().as_u16(),
+        headers: response_headers(res.headers()),
+        body: res.text().map_err(|e| e.to_string())?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::token::Token;
+    use crate::transport::{HttpRequest, HttpResponse};
+
+    #[test]
+    fn get_request_is_authorized() {
+        let token = Token::new("up:yeah:secret");
+        let request = HttpRequest::get("https://api.up.com.au/api/v1/accounts", &token)
+            .query(&[("page[size]".to_string(), "10".to_string())]);
+        assert_eq!(request.token.expose(), "up:yeah:secret");
+        assert!(!format!("{:?}", request).contains("secret"));
+        assert_eq!(
+            request.query,
+            vec![("page[size]".to_string(), "10".to_string())]
+        );
+    }
+
+    #[test]
+    fn parse_returns_body_on_error() {
+        let ok = HttpResponse {
+            status: 200,
+            headers: Vec::new(),
+            body: "{\"meta\": 1}".to_string(),
+        };
+        assert_eq!(
+            ok.parse::<serde_json::Value>().unwrap(),
+            serde_json::json!({ "meta": 1 })
+        );
+        let unauthorized = HttpResponse {
+            status: 401,
+            headers: Vec::new(),
+            body: "not authorized".to_string(),
+        };
+        assert_eq!(
+            unauthorized.parse::<serde_json::Value>().unwrap_err(),
+            "not authorized"
+        );
+    }
+}
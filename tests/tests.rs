@@ -1,20 +1,23 @@
 #[cfg(test)]
 mod tests {
-    use chrono::{DateTime, FixedOffset};
-    use serde_json::json;
-    use std::collections::HashMap;
+    use chrono::{DateTime, FixedOffset, NaiveDate};
+    use std::collections::{BTreeSet, HashMap, HashSet};
     use std::fs;
+    use async_trait::async_trait;
+    use std::sync::{Arc, Mutex};
+    use uprs::api_endpoints::ListTransactions;
+    use uprs::client::UpClient;
+    use uprs::dates::Tz;
     use uprs::models::*;
-    use uprs::request_sender::ApiResponse;
+    use uprs::request_sender::{ApiEndpoint, ApiRequest, ApiResponse};
+    use uprs::transport::{HttpRequest, HttpResponse, HttpTransport};
 
     #[tokio::test]
     async fn account_deserialization() {
         let json: String = fs::read_to_string("tests/example_json/list_accounts.json").unwrap();
-        let des: ApiResponse<Vec<Account>> = serde_json::from_str(&*json).unwrap();
+        let des: ApiResponse<Vec<Account>> = serde_json::from_str(&json).unwrap();
 
-        assert_eq!(
-            des,
-            ApiResponse {
+        let expected = ApiResponse {
                data: vec![
                    Account {
                        resource_type: "accounts".to_string(),
@@ -33,7 +36,7 @@ mod tests {
                        },
                        relationships: AccountRelationships {
                            transactions: AccountTransactionsRelationships {
-                               links: HashMap::<String, String>::from([("related".to_string(), "https://api.up.com.au/api/v1/accounts/1bcc9d36-ccdf-457d-9e40-cecde788abb4/transactions".to_string())])
+                               links: RelationshipLinks { self_: None, related: Some("https://api.up.com.au/api/v1/accounts/1bcc9d36-ccdf-457d-9e40-cecde788abb4/transactions".to_string()) }
                            }
                        },
                        links: HashMap::<String, String>::from([("self".to_string(), "https://api.up.com.au/api/v1/accounts/1bcc9d36-ccdf-457d-9e40-cecde788abb4".to_string())])
@@ -42,19 +45,25 @@ mod tests {
                 links: Some(HashMap::<String, Option<String>>::from([
                     ("prev".to_string(), None),
                     ("next".to_string(), Some("https://api.up.com.au/api/v1/accounts?page%5Bafter%5D=WyIyMDIyLTA3LTA1VDA3OjUyOjI1LjI5NDU0NjAwMFoiLCIxYmNjOWQzNi1jY2RmLTQ1N2QtOWU0MC1jZWNkZTc4OGFiYjQiXQ%3D%3D&page%5Bsize%5D=1".to_string()))
-                ]))
-            }
-        )
+                ])),
+                meta: None,
+                included: None
+            };
+
+        assert_eq!(des, expected);
+        for (actual, expected) in des.data.iter().zip(expected.data.iter()) {
+            assert_eq!(actual.attributes, expected.attributes);
+            assert_eq!(actual.relationships, expected.relationships);
+            assert_eq!(actual.links, expected.links);
+        }
     }
 
     #[tokio::test]
     async fn category_deserialization() {
         let json: String = fs::read_to_string("tests/example_json/list_categories.json").unwrap();
-        let des: ApiResponse<Vec<Category>> = serde_json::from_str(&*json).unwrap();
+        let des: ApiResponse<Vec<Category>> = serde_json::from_str(&json).unwrap();
 
-        assert_eq!(
-            des,
-            ApiResponse {
+        let expected = ApiResponse {
                 data: vec![
                         Category {
                             resource_type: "categories".to_string(),
@@ -64,19 +73,21 @@ mod tests {
                             },
                             relationships: CategoryRelationships {
                                 parent: ParentRelationship {
-                                    data: Some(HashMap::<String, String>::from([
-                                        ("id".to_string(), "good-life".to_string()),
-                                        ("type".to_string(), "categories".to_string())
-                                    ])),
-                                    links: Some(HashMap::<String, String>::from([
-                                        ("related".to_string(), "https://api.up.com.au/api/v1/categories/good-life".to_string())
-                                    ])),
+                                    data: Some(ResourceIdentifier {
+                                        resource_type: "categories".to_string(),
+                                        id: "good-life".to_string()
+                                    }),
+                                    links: Some(RelationshipLinks {
+                                        self_: None,
+                                        related: Some("https://api.up.com.au/api/v1/categories/good-life".to_string())
+                                    }),
                                 },
                                 children: ChildRelationship {
                                     data: Some(vec![]),
-                                    links: Some(HashMap::<String, String>::from([
-                                        ("related".to_string(), "https://api.up.com.au/api/v1/categories?filter%5Bparent%5D=hobbies".to_string())
-                                    ])),
+                                    links: Some(RelationshipLinks {
+                                        self_: None,
+                                        related: Some("https://api.up.com.au/api/v1/categories?filter%5Bparent%5D=hobbies".to_string())
+                                    }),
                                 }
                             },
                             links: Some(HashMap::<String, String>::from([
@@ -91,19 +102,21 @@ mod tests {
                         },
                         relationships: CategoryRelationships {
                             parent: ParentRelationship {
-                                data: Some(HashMap::<String, String>::from([
-                                    ("type".to_string(), "categories".to_string()),
-                                    ("id".to_string(), "good-life".to_string())
-                                ])),
-                                links: Some(HashMap::<String, String>::from([
-                                    ("related".to_string(), "https://api.up.com.au/api/v1/categories/good-life".to_string())
-                                ])),
+                                data: Some(ResourceIdentifier {
+                                    resource_type: "categories".to_string(),
+                                    id: "good-life".to_string()
+                                }),
+                                links: Some(RelationshipLinks {
+                                    self_: None,
+                                    related: Some("https://api.up.com.au/api/v1/categories/good-life".to_string())
+                                }),
                             },
                             children: ChildRelationship {
                                 data: Some(vec![]),
-                                links: Some(HashMap::<String, String>::from([
-                                    ("related".to_string(), "https://api.up.com.au/api/v1/categories?filter%5Bparent%5D=restaurants-and-cafes".to_string())
-                                ])),
+                                links: Some(RelationshipLinks {
+                                    self_: None,
+                                    related: Some("https://api.up.com.au/api/v1/categories?filter%5Bparent%5D=restaurants-and-cafes".to_string())
+                                }),
                             }
                         },
                         links: Some(HashMap::<String, String>::from([
@@ -111,48 +124,58 @@ mod tests {
                         ])),
                     },
                 ],
-                links: None
-            }
-        )
+                links: None,
+                meta: None,
+                included: None
+            };
+
+        assert_eq!(des, expected);
+        for (actual, expected) in des.data.iter().zip(expected.data.iter()) {
+            assert_eq!(actual.attributes, expected.attributes);
+            assert_eq!(actual.relationships, expected.relationships);
+            assert_eq!(actual.links, expected.links);
+        }
     }
 
     #[tokio::test]
     async fn tag_deserialization() {
         let json: String = fs::read_to_string("tests/example_json/list_tags.json").unwrap();
-        let des: ApiResponse<Vec<Tag>> = serde_json::from_str(&*json).unwrap();
+        let des: ApiResponse<Vec<Tag>> = serde_json::from_str(&json).unwrap();
 
-        assert_eq!(
-            des,
-            ApiResponse { data: vec![
+        let expected = ApiResponse { data: vec![
                 Tag {
                     resource_type: "tags".to_string(),
                     id: "Holiday".to_string(),
-                    relationships: TagRelationships { transactions: TagTransactionRelationships { links: HashMap::<String, String>::from([
-                        ("related".to_string(), "https://api.up.com.au/api/v1/transactions?filter%5Btag%5D=Holiday".to_string())
-                    ]) } }
+                    relationships: TagRelationships { transactions: TagTransactionRelationships { links: RelationshipLinks {
+                        self_: None,
+                        related: Some("https://api.up.com.au/api/v1/transactions?filter%5Btag%5D=Holiday".to_string())
+                    } } }
                 },
                 Tag {
                     resource_type: "tags".to_string(),
                     id: "Pizza Night".to_string(),
-                    relationships: TagRelationships { transactions: TagTransactionRelationships { links: HashMap::<String, String>::from([
-                        ("related".to_string(), "https://api.up.com.au/api/v1/transactions?filter%5Btag%5D=Pizza+Night".to_string())
-                    ]) } }
+                    relationships: TagRelationships { transactions: TagTransactionRelationships { links: RelationshipLinks {
+                        self_: None,
+                        related: Some("https://api.up.com.au/api/v1/transactions?filter%5Btag%5D=Pizza+Night".to_string())
+                    } } }
                 }
             ], links: Some(HashMap::<String, Option<String>>::from([
                 ("prev".to_string(), None),
                 ("next".to_string(), Some("https://api.up.com.au/api/v1/tags?page%5Bafter%5D=WyJQaXp6YSBOaWdodCJd&page%5Bsize%5D=2".to_string()))
-                ])) }
-        )
+                ])), meta: None, included: None };
+
+        assert_eq!(des, expected);
+        for (actual, expected) in des.data.iter().zip(expected.data.iter()) {
+            assert_eq!(actual.relationships, expected.relationships);
+        }
     }
 
     #[tokio::test]
     async fn transaction_deserialization() {
         let json: String = fs::read_to_string("tests/example_json/list_transactions.json").unwrap();
-        let des: ApiResponse<Vec<Transaction>> = serde_json::from_str(&*json).unwrap();
+        let des: ApiResponse<Vec<Transaction>> = serde_json::from_str(&json).unwrap();
 
-        assert_eq!(
-            des,
-            ApiResponse {
+        let expected = ApiResponse {
                 data: vec![Transaction {
                     resource_type: "transactions".to_string(),
                     id: "13a25bb2-7290-43ce-a9e8-c9901c58895d".to_string(),
@@ -185,13 +208,14 @@ mod tests {
                     },
                     relationships: TransactionRelationships {
                         account: TransactionAccountRelationship {
-                            data: TransactionAccountRelationshipData {
+                            data: ResourceIdentifier {
                                 resource_type: "accounts".to_string(),
                                 id: "0707a248-76c7-4fb8-8858-18b6489fded3".to_string()
                             },
-                            links: Some(HashMap::<String, String>::from([
-                                ("related".to_string(), "https://api.up.com.au/api/v1/accounts/0707a248-76c7-4fb8-8858-18b6489fded3".to_string())
-                            ]))
+                            links: Some(RelationshipLinks {
+                                self_: None,
+                                related: Some("https://api.up.com.au/api/v1/accounts/0707a248-76c7-4fb8-8858-18b6489fded3".to_string())
+                            })
                         },
                         transfer_account: TransactionTransferAccountRelationship {
                             data: None,
@@ -199,9 +223,10 @@ mod tests {
                         },
                         category: TransactionCategoryRelationship {
                             data: None,
-                            links: Some(HashMap::<String, String>::from([
-                                ("self".to_string(), "https://api.up.com.au/api/v1/transactions/13a25bb2-7290-43ce-a9e8-c9901c58895d/relationships/category".to_string())
-                            ]))
+                            links: Some(RelationshipLinks {
+                                self_: Some("https://api.up.com.au/api/v1/transactions/13a25bb2-7290-43ce-a9e8-c9901c58895d/relationships/category".to_string()),
+                                related: None
+                            })
                         },
                         parent_category: TransactionCategoryRelationship {
                             data: None,
@@ -209,11 +234,12 @@ mod tests {
                         },
                         tags: TransactionTagsRelationship {
                             data: vec![
-                                TransactionTagsRelationshipData { resource_type: "tags".to_string(), id: "Pizza Night".to_string() }
+                                ResourceIdentifier { resource_type: "tags".to_string(), id: "Pizza Night".to_string() }
                             ],
-                            links: Some(HashMap::<String, String>::from([
-                                ("self".to_string(), "https://api.up.com.au/api/v1/transactions/13a25bb2-7290-43ce-a9e8-c9901c58895d/relationships/tags".to_string())
-                            ]))
+                            links: Some(RelationshipLinks {
+                                self_: Some("https://api.up.com.au/api/v1/transactions/13a25bb2-7290-43ce-a9e8-c9901c58895d/relationships/tags".to_string()),
+                                related: None
+                            })
                         }
                     },
                     links: Some(HashMap::<String, String>::from([
@@ -223,8 +249,147 @@ mod tests {
                 links: Some(HashMap::<String, Option<String>>::from([
                     ("prev".to_string(), None),
                     ("next".to_string(), None)
-                ]))
-            }
-        )
+                ])),
+                meta: None,
+                included: None
+            };
+
+        assert_eq!(des, expected);
+        for (actual, expected) in des.data.iter().zip(expected.data.iter()) {
+            assert_eq!(actual.attributes, expected.attributes);
+            assert_eq!(actual.relationships, expected.relationships);
+            assert_eq!(actual.links, expected.links);
+        }
+    }
+
+    #[tokio::test]
+    async fn category_relationship_accessors() {
+        let json: String = fs::read_to_string("tests/example_json/list_categories.json").unwrap();
+        let des: ApiResponse<Vec<Category>> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(des.data[0].parent_id(), Some(&"good-life".to_string()));
+        assert!(des.data[0].child_ids().is_empty());
+    }
+
+    #[tokio::test]
+    async fn transaction_relationship_accessors() {
+        let json: String = fs::read_to_string("tests/example_json/list_transactions.json").unwrap();
+        let des: ApiResponse<Vec<Transaction>> = serde_json::from_str(&json).unwrap();
+        let transaction = &des.data[0];
+
+        assert_eq!(
+            transaction.account_id(),
+            "0707a248-76c7-4fb8-8858-18b6489fded3"
+        );
+        assert_eq!(transaction.transfer_account_id(), None);
+        assert_eq!(transaction.category_id(), None);
+        assert_eq!(transaction.parent_category_id(), None);
+        assert_eq!(transaction.tag_ids(), vec!["Pizza Night"]);
+    }
+
+    #[tokio::test]
+    async fn transaction_identity_and_ordering() {
+        let json: String = fs::read_to_string("tests/example_json/list_transactions.json").unwrap();
+        let des: ApiResponse<Vec<Transaction>> = serde_json::from_str(&json).unwrap();
+        let first = des.data[0].clone();
+
+        let mut held_snapshot = first.clone();
+        held_snapshot.attributes.status = TransactionStatus::Held;
+        assert_eq!(first, held_snapshot);
+        assert_eq!(HashSet::from([first.clone(), held_snapshot]).len(), 1);
+
+        let mut earlier = first.clone();
+        earlier.id = "zzzz".to_string();
+        earlier.attributes.created_at =
+            DateTime::<FixedOffset>::parse_from_rfc3339("2022-07-06T05:20:36+10:00").unwrap();
+        let mut same_time = first.clone();
+        same_time.id = "0000".to_string();
+
+        let ordered: Vec<String> = BTreeSet::from([first, earlier, same_time])
+            .into_iter()
+            .map(|transaction| transaction.id)
+            .collect();
+        assert_eq!(
+            ordered,
+            vec!["zzzz", "0000", "13a25bb2-7290-43ce-a9e8-c9901c58895d"]
+        );
+    }
+
+    #[tokio::test]
+    async fn transaction_date_filters() {
+        let api_key = "token".to_string();
+        let list = ListTransactions::new(&api_key).in_month(2022, 10);
+        assert_eq!(
+            list.get_params(),
+            &vec![
+                ("filter[since]".to_string(), "2022-10-01T00:00:00+10:00".to_string()),
+                ("filter[until]".to_string(), "2022-11-01T00:00:00+11:00".to_string())
+            ]
+        );
+
+        let list = ListTransactions::new(&api_key)
+            .timezone(Tz::Australia__Perth)
+            .on_date(NaiveDate::from_ymd_opt(2022, 10, 2).unwrap());
+        assert_eq!(
+            list.get_params(),
+            &vec![
+                ("filter[since]".to_string(), "2022-10-02T00:00:00+08:00".to_string()),
+                ("filter[until]".to_string(), "2022-10-03T00:00:00+08:00".to_string())
+            ]
+        );
+
+        let later = ListTransactions::new(&api_key)
+            .on_date(NaiveDate::from_ymd_opt(2022, 10, 2).unwrap())
+            .timezone(Tz::Australia__Perth);
+        assert_eq!(later.get_params(), list.get_params());
+
+        let list = ListTransactions::new(&api_key).last_n_days(0);
+        assert!(list.get_params().is_empty());
+    }
+
+    /// Serves the first page of accounts from the example JSON and an empty second page.
+    struct FakeTransport {
+        requests: Arc<Mutex<Vec<HttpRequest>>>,
+    }
+
+    #[async_trait]
+    impl HttpTransport for FakeTransport {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse, String> {
+            self.requests.lock().unwrap().push(request.clone());
+            let body = if request.url.contains("page%5Bafter%5D") {
+                r#"{"data": [], "links": {"prev": null, "next": null}}"#.to_string()
+            } else {
+                fs::read_to_string("tests/example_json/list_accounts.json").unwrap()
+            };
+            Ok(HttpResponse {
+                status: 200,
+                headers: Vec::new(),
+                body,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_transport_paginates() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let client = UpClient::new(&"token".to_string()).transport(FakeTransport {
+            requests: requests.clone(),
+        });
+
+        let accounts = client.list_accounts().page_size(1).send().await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].attributes.display_name, "Spending");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].url, "https://api.up.com.au/api/v1/accounts");
+        assert_eq!(
+            requests[0].query,
+            vec![("page[size]".to_string(), "1".to_string())]
+        );
+        assert!(requests[1].query.is_empty());
+        for request in requests.iter() {
+            assert_eq!(request.token.expose(), "token");
+        }
     }
 }